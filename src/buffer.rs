@@ -0,0 +1,59 @@
+use ggez::{
+  Context,
+  graphics::{self, Point2},
+  GameResult,
+};
+
+use path::{
+  track::{
+    Track,
+    TrackPiece,
+  },
+  Pos,
+};
+use train::{next_track, prev_track};
+
+// how far back along the track to look for which way the buffers face
+const BACK: f32 = 8.;
+// half the width of the beam across the track
+const WIDTH: f32 = 8.;
+
+/// the end of a line, trains brake for it and turn around in front of it
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
+pub struct Buffer {
+  // where the rails end
+  pub pos: Pos,
+  // a little way back along the track
+  back: Pos,
+}
+
+impl Buffer {
+  /// one at every track end nothing carries on from
+  pub fn find(tracks: &[Track]) -> Vec<Buffer> {
+    let mut buffers = Vec::new();
+
+    for (i, track) in tracks.iter().enumerate() {
+      let back = BACK.min(track.len());
+
+      if prev_track(tracks, i).is_none() {
+        buffers.push(Buffer { pos: track.at(0.), back: track.at(back) });
+      }
+      if next_track(tracks, i).is_none() {
+        buffers.push(Buffer { pos: track.at(track.len()), back: track.at(track.len() - back) });
+      }
+    }
+
+    buffers
+  }
+
+  /// a beam across the end of the rails
+  pub fn draw(&self, ctx: &mut Context) -> GameResult<()> {
+    let (x, y) = self.pos.to_float();
+    let (bx, by) = self.back.to_float();
+    let len = ((x - bx).powi(2) + (y - by).powi(2)).sqrt().max(1.);
+    let (sx, sy) = ((by - y) / len * WIDTH, (x - bx) / len * WIDTH);
+
+    graphics::set_color(ctx, [0.7, 0.2, 0.1, 1.0].into())?;
+    graphics::line(ctx, &[Point2::new(x + sx, y + sy), Point2::new(x - sx, y - sy)], 4.)
+  }
+}
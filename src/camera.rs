@@ -0,0 +1,82 @@
+use ggez::{
+  graphics::{self, Rect},
+  GameResult,
+  Context,
+};
+
+use path::Pos;
+
+const ZOOM_STEP: f32 = 1.1;
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 4.;
+
+// how quickly the camera catches up with what it's following, higher is snappier
+const FOLLOW_SPEED: f32 = 4.;
+
+pub struct Camera {
+  // world position of the top left corner of the screen
+  pos: (f32, f32),
+  zoom: f32,
+  size: (f32, f32),
+}
+
+impl Camera {
+  pub fn new(size: (u32, u32)) -> Self {
+    Camera {
+      pos: (0., 0.),
+      zoom: 1.,
+      size: (size.0 as f32, size.1 as f32),
+    }
+  }
+
+  /// size of the visible part of the world
+  fn view_size(&self) -> (f32, f32) {
+    (self.size.0 / self.zoom, self.size.1 / self.zoom)
+  }
+
+  fn to_world_f(&self, (x, y): (i32, i32)) -> (f32, f32) {
+    (self.pos.0 + x as f32 / self.zoom, self.pos.1 + y as f32 / self.zoom)
+  }
+
+  pub fn to_world(&self, screen: (i32, i32)) -> Pos {
+    let (x, y) = self.to_world_f(screen);
+    Pos(x as i32, y as i32)
+  }
+
+  pub fn pan(&mut self, dx: i32, dy: i32) {
+    self.pos.0 -= dx as f32 / self.zoom;
+    self.pos.1 -= dy as f32 / self.zoom;
+  }
+
+  /// zooms in (positive steps) or out while keeping the point under `screen` in place
+  pub fn zoom_at(&mut self, screen: (i32, i32), steps: i32) {
+    let before = self.to_world_f(screen);
+
+    self.zoom = (self.zoom * ZOOM_STEP.powi(steps)).max(MIN_ZOOM).min(MAX_ZOOM);
+
+    let after = self.to_world_f(screen);
+    self.pos.0 += before.0 - after.0;
+    self.pos.1 += before.1 - after.1;
+  }
+
+  /// moves the center of the view towards `target`, easing in over time
+  pub fn follow(&mut self, target: (f32, f32), delta: f32) {
+    let (w, h) = self.view_size();
+    let goal = (target.0 - w / 2., target.1 - h / 2.);
+    let t = 1. - (-FOLLOW_SPEED * delta).exp();
+
+    self.pos.0 += (goal.0 - self.pos.0) * t;
+    self.pos.1 += (goal.1 - self.pos.1) * t;
+  }
+
+  /// draw in world coordinates from here on
+  pub fn apply(&self, ctx: &mut Context) -> GameResult<()> {
+    let (w, h) = self.view_size();
+    graphics::set_screen_coordinates(ctx, Rect::new(self.pos.0, self.pos.1, w, h))
+  }
+
+  /// draw in screen coordinates from here on, for overlays
+  pub fn reset(&self, ctx: &mut Context) -> GameResult<()> {
+    graphics::set_screen_coordinates(ctx, Rect::new(0., 0., self.size.0, self.size.1))
+  }
+}
@@ -0,0 +1,89 @@
+use path::Pos;
+use world::TICK;
+
+/// things trains carry between stations
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq, Eq, Hash)]
+pub enum Cargo {
+  Passengers,
+  Mail,
+  Coal,
+  Grain,
+  Goods,
+}
+
+impl Cargo {
+  pub const ALL: [Cargo; 5] = [
+    Cargo::Passengers,
+    Cargo::Mail,
+    Cargo::Coal,
+    Cargo::Grain,
+    Cargo::Goods,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Cargo::Passengers => "passengers",
+      Cargo::Mail => "mail",
+      Cargo::Coal => "coal",
+      Cargo::Grain => "grain",
+      Cargo::Goods => "goods",
+    }
+  }
+
+  /// whether any station takes it, the rest needs an industry that wants it
+  pub fn everywhere(&self) -> bool {
+    match self {
+      Cargo::Passengers | Cargo::Mail => true,
+      _ => false,
+    }
+  }
+
+  /// paid per unit and cell of distance for a quick delivery
+  fn rate(&self) -> f32 {
+    match self {
+      Cargo::Passengers => 3.,
+      Cargo::Mail => 4.5,
+      Cargo::Coal => 2.,
+      Cargo::Grain => 2.5,
+      Cargo::Goods => 4.,
+    }
+  }
+
+  /// seconds until a delivery is only worth the minimum
+  fn patience(&self) -> f32 {
+    match self {
+      Cargo::Passengers => 60.,
+      Cargo::Mail => 40.,
+      Cargo::Coal => 240.,
+      Cargo::Grain => 120.,
+      Cargo::Goods => 90.,
+    }
+  }
+}
+
+// slow deliveries still earn this much of the full rate
+const MIN_PAY: f32 = 0.25;
+
+/// an amount of cargo on its way from one station to another
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
+pub struct Parcel {
+  pub cargo: Cargo,
+  pub amount: u32,
+  // where it got loaded and when
+  pub from: Pos,
+  pub loaded: u64,
+}
+
+impl Parcel {
+  /// what delivering it to `to` at `tick` earns
+  pub fn revenue(&self, to: Pos, tick: u64, cell_size: i16) -> i64 {
+    let (fx, fy) = self.from.to_float();
+    let (tx, ty) = to.to_float();
+    let cells = ((tx - fx).powi(2) + (ty - fy).powi(2)).sqrt() / cell_size as f32;
+
+    let seconds = (tick - self.loaded) as f32 * TICK;
+    let speed = (1. - seconds / self.cargo.patience()).max(MIN_PAY);
+
+    (self.amount as f32 * self.cargo.rate() * cells * speed).round() as i64
+  }
+}
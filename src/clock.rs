@@ -0,0 +1,77 @@
+use world::TICK;
+
+// longer frames than this are cut short so a hitch doesn't queue up endless steps
+const MAX_FRAME: f32 = 0.25;
+
+const RATES: [u32; 4] = [1, 2, 4, 8];
+
+/// turns frame times into a number of fixed simulation steps
+pub struct Clock {
+  paused: bool,
+  step: bool,
+  rate: usize,
+  // simulation time that hasn't been stepped yet
+  acc: f32,
+}
+
+impl Clock {
+  pub fn new() -> Self {
+    Clock {
+      paused: false,
+      step: false,
+      rate: 0,
+      acc: 0.,
+    }
+  }
+
+  pub fn toggle_pause(&mut self) {
+    self.paused = !self.paused;
+  }
+
+  /// advances a paused simulation by a single step on the next frame
+  pub fn step(&mut self) {
+    self.paused = true;
+    self.step = true;
+  }
+
+  /// picks the nth entry of 1x, 2x, 4x and 8x
+  pub fn set_rate(&mut self, rate: usize) {
+    if rate < RATES.len() {
+      self.rate = rate;
+    }
+  }
+
+  /// how many steps to run for a frame that took `frame` seconds
+  pub fn ticks(&mut self, frame: f32) -> u32 {
+    if self.paused {
+      self.acc = 0.;
+
+      if self.step {
+        self.step = false;
+        return 1;
+      }
+
+      return 0;
+    }
+
+    self.acc += frame.min(MAX_FRAME) * RATES[self.rate] as f32;
+
+    let ticks = (self.acc / TICK) as u32;
+    self.acc -= ticks as f32 * TICK;
+
+    ticks
+  }
+
+  /// how far along we are between the last step and the next one
+  pub fn alpha(&self) -> f32 {
+    self.acc / TICK
+  }
+
+  pub fn label(&self) -> String {
+    if self.paused {
+      "paused".to_owned()
+    } else {
+      format!("speed {}x", RATES[self.rate])
+    }
+  }
+}
@@ -0,0 +1,185 @@
+use std::{
+  collections::HashMap,
+  fs::File,
+};
+
+use ron;
+
+use ggez::{
+  GameResult,
+  GameError,
+};
+
+use {arg, flag};
+use input::{Action, Bindings};
+use mapgen;
+use world::World;
+
+/// the shape of the world tracks are built on
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Grid {
+  // in cells
+  pub size: (i16, i16),
+  pub cell_size: i16,
+  // in cells, turns only line up with the grid for multiples of 2.5
+  pub turn_radius: f32,
+  // how many lines a turn is drawn with
+  pub turn_divisions: i32,
+}
+
+impl Grid {
+  /// size of the world in pixels
+  pub fn world_size(&self) -> (i32, i32) {
+    (self.size.0 as i32 * self.cell_size as i32, self.size.1 as i32 * self.cell_size as i32)
+  }
+
+  pub fn validate(&self) -> GameResult<()> {
+    let turns = self.turn_radius / 2.5;
+
+    if self.size.0 <= 0 || self.size.1 <= 0 || self.cell_size <= 0 {
+      Err(GameError::ConfigError("the grid has to have a size".to_owned()))
+    } else if turns < 1. || turns.fract() != 0. {
+      Err(GameError::ConfigError(format!("turn radius {} is not a multiple of 2.5", self.turn_radius)))
+    } else if self.turn_divisions < 1 {
+      Err(GameError::ConfigError("turns need at least one division".to_owned()))
+    } else {
+      Ok(())
+    }
+  }
+}
+
+impl Default for Grid {
+  fn default() -> Self {
+    Grid {
+      size: (40, 25),
+      cell_size: 32,
+      turn_radius: 2.5,
+      turn_divisions: 8,
+    }
+  }
+}
+
+/// settings, read from a ron file and overridden by command line flags:
+///
+/// ```text
+/// (
+///   grid: (size: (80, 50), cell_size: 16),
+///   screen_size: Some((1280, 800)),
+///   budget: Some(5000),
+///   blank: false,
+///   derailments: true,
+///   bindings: {
+///     "MouseRight": Pan,
+///     "MouseMiddle": Unbound,
+///     "Q": Cancel,
+///     "T": Pick(Track),
+///   },
+/// )
+/// ```
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+  pub grid: Grid,
+  // defaults to fitting the whole grid
+  screen_size: Option<(u32, u32)>,
+  // money for building track, unlimited without one
+  pub budget: Option<i64>,
+  // key and button names to actions, on top of the default bindings
+  bindings: HashMap<String, Action>,
+  // starts new games on an empty grid instead of a generated map
+  pub blank: bool,
+  // trains only keep to the limits put up and come off turns they take too fast
+  pub derailments: bool,
+}
+
+impl Config {
+  /// reads `--settings` and the flags for the single values
+  pub fn from_args() -> GameResult<Config> {
+    let mut config = match arg("--settings") {
+      Some(path) => Config::load(&path)?,
+      None => Config::default(),
+    };
+
+    if let Some(size) = arg("--grid-size") {
+      let (w, h) = parse_pair(&size)?;
+      let max = ::std::i16::MAX as u32;
+
+      if w > max || h > max {
+        return Err(GameError::ConfigError(format!("grid size {} is too big, at most {} cells each way", size, max)));
+      }
+      config.grid.size = (w as i16, h as i16);
+    }
+    if let Some(size) = arg("--cell-size") {
+      config.grid.cell_size = parse(&size)?;
+    }
+    if let Some(radius) = arg("--turn-radius") {
+      config.grid.turn_radius = parse(&radius)?;
+    }
+    if let Some(divisions) = arg("--turn-divisions") {
+      config.grid.turn_divisions = parse(&divisions)?;
+    }
+    if let Some(size) = arg("--screen-size") {
+      config.screen_size = Some(parse_pair(&size)?);
+    }
+    if let Some(budget) = arg("--budget") {
+      config.budget = Some(parse(&budget)?);
+    }
+    if flag("--blank") {
+      config.blank = true;
+    }
+    if flag("--derailments") {
+      config.derailments = true;
+    }
+
+    config.grid.validate()?;
+    config.bindings()?;
+
+    Ok(config)
+  }
+
+  pub fn load(path: &str) -> GameResult<Config> {
+    let file = File::open(path)?;
+
+    ron::de::from_reader(file)
+        .map_err(|e| GameError::ConfigError(format!("invalid settings {}: {}", path, e)))
+  }
+
+  pub fn bindings(&self) -> GameResult<Bindings> {
+    Bindings::new(&self.bindings)
+  }
+
+  /// a new world made with these settings
+  pub fn world(&self, seed: u32) -> World {
+    let mut world = World::new(seed, self.grid);
+    world.budget = self.budget;
+    world.derailments = self.derailments;
+
+    if !self.blank {
+      mapgen::generate(&mut world);
+    }
+
+    world
+  }
+
+  pub fn screen_size(&self) -> (u32, u32) {
+    self.screen_size.unwrap_or_else(|| {
+      let (w, h) = self.grid.world_size();
+      (w as u32, h as u32)
+    })
+  }
+}
+
+fn parse<T: ::std::str::FromStr>(value: &str) -> GameResult<T> {
+  value.parse().map_err(|_| GameError::ConfigError(format!("invalid value {}", value)))
+}
+
+/// reads things like `1280x800`
+fn parse_pair(value: &str) -> GameResult<(u32, u32)> {
+  let mut parts = value.split('x');
+
+  match (parts.next(), parts.next(), parts.next()) {
+    (Some(w), Some(h), None) => Ok((parse(w)?, parse(h)?)),
+    _ => Err(GameError::ConfigError(format!("invalid size {}, expected WxH", value))),
+  }
+}
@@ -0,0 +1,148 @@
+use ggez::{
+  Context,
+  graphics::{self, Point2},
+  GameResult,
+};
+
+use path::{
+  track::{
+    Track,
+    TrackPiece,
+  },
+  Pos,
+};
+use train::Train;
+
+// how close a train can come to a crossing without being on it
+const CLEARANCE: f32 = 12.;
+// trains closer to a crossing than this have it to themselves
+const CLAIM: f32 = CLEARANCE + 3.;
+// how far short of a crossing someone else has trains wait, a bit further out
+// than the claim so waiting trains never hold each other up
+pub const WAIT: f32 = CLAIM + 1.;
+// half the width of the diamond drawn on a crossing
+const SIZE: f32 = 7.;
+
+/// two straight or diagonal pieces crossing on the level, only one train can be on it at a time
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
+pub struct Crossing {
+  pub pos: Pos,
+  // where the crossing is along each of the two pieces
+  pub spots: [(usize, f32); 2],
+  pub occupied: bool,
+}
+
+impl Crossing {
+  /// every place two pieces of `tracks` cross
+  pub fn find(tracks: &[Track]) -> Vec<Crossing> {
+    let mut crossings = Vec::new();
+
+    for (i, a) in tracks.iter().enumerate().filter(|&(_, track)| level(track)) {
+      for (j, b) in tracks.iter().enumerate().skip(i + 1).filter(|&(_, track)| level(track)) {
+        if let Some((t, u)) = intersect(a, b) {
+          crossings.push(Crossing {
+            pos: a.lerp(t),
+            spots: [(i, t * a.len()), (j, u * b.len())],
+            occupied: false,
+          });
+        }
+      }
+    }
+
+    crossings
+  }
+
+  /// whether the train is on the crossing, about to get on it or still too close to it
+  /// for anything else to cross
+  pub fn taken_by(&self, train: &Train, tracks: &Vec<Track>) -> bool {
+    self.claim(train, tracks) > 0
+  }
+
+  /// whether `other` keeps `train` off the crossing, both go with their index in the list
+  ///
+  /// when both have taken it the one with the stronger claim goes first and otherwise the one
+  /// with the lower index, so trains that start out close to it don't wait for each other forever
+  pub fn holds_back(&self, (i, train): (usize, &Train), (j, other): (usize, &Train), tracks: &Vec<Track>) -> bool {
+    let (mine, theirs) = (self.claim(train, tracks), self.claim(other, tracks));
+
+    theirs > 0 && (theirs > mine || theirs == mine && j < i)
+  }
+
+  /// how strong a hold the train has on the crossing, from lying across it, to being too close
+  /// to it for anything else to cross, to about to get on it, down to none
+  fn claim(&self, train: &Train, tracks: &Vec<Track>) -> u8 {
+    if self.spots.iter().any(|&(track, dist)| train.covers(track, dist)) {
+      3
+    } else if train.near(self.pos, CLEARANCE) {
+      2
+    } else if self.ahead(train, tracks).map_or(false, |dist| dist < CLAIM) {
+      1
+    } else {
+      0
+    }
+  }
+
+  /// how far the train has to go to get onto the crossing, if it's heading there
+  pub fn ahead(&self, train: &Train, tracks: &Vec<Track>) -> Option<f32> {
+    self.spots.iter()
+        .filter_map(|&(track, dist)| train.distance_to(tracks, track, dist))
+        .fold(None, |min, dist| Some(min.map_or(dist, |min: f32| min.min(dist))))
+  }
+
+  pub fn draw(&self, ctx: &mut Context) -> GameResult<()> {
+    if self.occupied {
+      graphics::set_color(ctx, [0.9, 0.5, 0.1, 1.0].into())?;
+    } else {
+      graphics::set_color(ctx, [0.3, 0.3, 0.3, 1.0].into())?;
+    }
+
+    let (x, y) = self.pos.to_float();
+    let points = [
+      Point2::new(x, y - SIZE),
+      Point2::new(x + SIZE, y),
+      Point2::new(x, y + SIZE),
+      Point2::new(x - SIZE, y),
+      Point2::new(x, y - SIZE),
+    ];
+
+    graphics::line(ctx, &points, 2.)
+  }
+}
+
+/// only pieces at ground level that go straight can cross each other
+fn level(track: &Track) -> bool {
+  match track {
+    Track::Strt(_) | Track::Diag(_) => true,
+    _ => false,
+  }
+}
+
+/// how far along each piece they cross, if they do, an end only counts for the piece it starts
+/// so a crossing on a joint isn't found twice
+fn intersect(a: &Track, b: &Track) -> Option<(f32, f32)> {
+  let (Pos(ax, ay), Pos(bx, by)) = (a.lerp(0.), b.lerp(0.));
+  let (Pos(aex, aey), Pos(bex, bey)) = (a.lerp(1.), b.lerp(1.));
+
+  let (rx, ry) = ((aex - ax) as i64, (aey - ay) as i64);
+  let (sx, sy) = ((bex - bx) as i64, (bey - by) as i64);
+  let (qx, qy) = ((bx - ax) as i64, (by - ay) as i64);
+
+  // parallel pieces, joined up or not, never cross
+  let denom = rx * sy - ry * sx;
+  if denom == 0 {
+    return None;
+  }
+
+  let (mut t, mut u, mut denom) = (qx * sy - qy * sx, qx * ry - qy * rx, denom);
+  if denom < 0 {
+    t = -t;
+    u = -u;
+    denom = -denom;
+  }
+
+  if t >= 0 && t < denom && u >= 0 && u < denom {
+    Some((t as f32 / denom as f32, u as f32 / denom as f32))
+  } else {
+    None
+  }
+}
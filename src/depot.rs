@@ -0,0 +1,158 @@
+use ggez::{
+  Context,
+  graphics::{self, DrawMode, Point2},
+  GameResult,
+};
+
+use path::{
+  track::{
+    Track,
+    TrackPiece,
+  },
+  Pos,
+};
+use train::{
+  spawn,
+  Train,
+  next_track,
+  prev_track,
+};
+
+// how close to the end of the rails a train has to turn around to call at the depot
+pub const REACH: f32 = 24.;
+// how far back along the track to look for which way the shed faces
+const BACK: f32 = 8.;
+// the shed past the end of the rails, along and across the track
+const LENGTH: f32 = 40.;
+const WIDTH: f32 = 28.;
+
+/// a train put away in a depot
+struct Stored {
+  train: Train,
+  // where its segments go along the line when it comes back out, see `Train::layout`
+  offsets: Vec<f32>,
+}
+
+/// a shed at the end of a line where trains get bought, put away and looked after
+pub struct Depot {
+  pub track: usize,
+  // whether it's at the end of the piece or at its start
+  pub at_end: bool,
+  trains: Vec<Stored>,
+}
+
+impl Depot {
+  pub fn new(track: usize, at_end: bool) -> Self {
+    Depot {
+      track,
+      at_end,
+      trains: Vec::new(),
+    }
+  }
+
+  /// whether nothing carries on from that end of `track`, depots only go on the end of a line
+  pub fn fits(tracks: &[Track], track: usize, at_end: bool) -> bool {
+    if at_end { next_track(tracks, track).is_none() } else { prev_track(tracks, track).is_none() }
+  }
+
+  /// how far along its piece the rails run into it
+  pub fn dist(&self, tracks: &Vec<Track>) -> f32 {
+    if self.at_end { tracks[self.track].len() } else { 0. }
+  }
+
+  pub fn pos(&self, tracks: &Vec<Track>) -> Pos {
+    tracks[self.track].at(self.dist(tracks))
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.trains.is_empty()
+  }
+
+  pub fn len(&self) -> usize {
+    self.trains.len()
+  }
+
+  /// takes a train in off the line
+  pub fn store(&mut self, train: Train, tracks: &Vec<Track>) {
+    let offsets = train.layout(tracks);
+    self.add(train, offsets);
+  }
+
+  /// takes in a train that was never on the line, `offsets` say how its segments are laid out
+  pub fn add(&mut self, train: Train, offsets: Vec<f32>) {
+    self.trains.push(Stored { train, offsets });
+  }
+
+  /// puts train `i` out on the line, facing away from the depot, as long as there's room for
+  /// it clear of `others` and electric trains have wires the whole way
+  pub fn release(&mut self, i: usize, tracks: &Vec<Track>, wired: &[bool], others: &[Train]) -> Option<Train> {
+    if i >= self.trains.len() {
+      return None;
+    }
+
+    let Stored { mut train, offsets } = self.trains.remove(i);
+    let len = offsets.iter().cloned().fold(0., f32::max);
+    let needs_wires = train.traction().map_or(false, |traction| traction.needs_wires());
+    let powered = |track: usize| !needs_wires || wired[track];
+
+    // the train runs out from the end of the rails, so that's where the room is counted from
+    if spawn::fits(tracks, self.track, self.dist(tracks), len, self.at_end, powered) {
+      let (dist, forward) = if self.at_end { (tracks[self.track].len() - len, false) } else { (0., true) };
+      train.rerail(tracks, self.track, dist, forward, &offsets);
+
+      if !others.iter().any(|other| other.touches(&train)) {
+        return Some(train);
+      }
+    }
+
+    self.trains.insert(i, Stored { train, offsets });
+    None
+  }
+
+  /// lines for the depot panel, `picked` is marked
+  pub fn inspect(&self, picked: usize) -> Vec<String> {
+    let mut lines = vec![format!("depot ({} trains)", self.trains.len())];
+
+    for (i, stored) in self.trains.iter().enumerate() {
+      let train = &stored.train;
+      let marker = if i == picked { ">" } else { " " };
+      let engine = train.traction().map_or("wagons", |traction| traction.name());
+
+      lines.push(format!("{} {}: {}, {} cars, {}", marker, i + 1, engine, train.cars(), train.condition()));
+    }
+
+    lines
+  }
+
+  /// a shed past the end of the rails
+  pub fn draw(&self, ctx: &mut Context, tracks: &Vec<Track>) -> GameResult<()> {
+    let track = &tracks[self.track];
+    let back = BACK.min(track.len());
+    let (end, behind) = if self.at_end {
+      (track.at(track.len()), track.at(track.len() - back))
+    } else {
+      (track.at(0.), track.at(back))
+    };
+
+    let (x, y) = end.to_float();
+    let (bx, by) = behind.to_float();
+    let len = ((x - bx).powi(2) + (y - by).powi(2)).sqrt().max(1.);
+    // along the track away from the rails and across it
+    let (ax, ay) = ((x - bx) / len, (y - by) / len);
+    let (sx, sy) = (-ay * WIDTH / 2., ax * WIDTH / 2.);
+    let (fx, fy) = (x + ax * LENGTH, y + ay * LENGTH);
+
+    let corners = [
+      Point2::new(x + sx, y + sy),
+      Point2::new(fx + sx, fy + sy),
+      Point2::new(fx - sx, fy - sy),
+      Point2::new(x - sx, y - sy),
+    ];
+
+    graphics::set_color(ctx, [0.5, 0.35, 0.25, 0.9].into())?;
+    graphics::polygon(ctx, DrawMode::Fill, &corners)?;
+
+    graphics::set_color(ctx, [0.0, 0.0, 0.0, 1.0].into())?;
+    graphics::polygon(ctx, DrawMode::Line(1.), &corners)
+  }
+}
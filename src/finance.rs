@@ -0,0 +1,83 @@
+use world::{TICK, TICKS_PER_SECOND};
+
+/// ticks in a finance period, a minute of simulated time
+pub const PERIOD: u64 = 60 * TICKS_PER_SECOND;
+// how many past periods are kept around
+const HISTORY: usize = 6;
+
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq, Default)]
+pub struct Ledger {
+  pub income: i64,
+  pub expenses: i64,
+}
+
+impl Ledger {
+  pub fn profit(&self) -> i64 {
+    self.income - self.expenses
+  }
+}
+
+/// money coming in and going out, per period
+pub struct Finances {
+  pub current: Ledger,
+  // oldest first
+  history: Vec<Ledger>,
+}
+
+impl Finances {
+  pub fn new() -> Self {
+    Finances {
+      current: Ledger::default(),
+      history: Vec::new(),
+    }
+  }
+
+  pub fn earn(&mut self, amount: i64) {
+    self.current.income += amount;
+  }
+
+  pub fn pay(&mut self, amount: i64) {
+    self.current.expenses += amount;
+  }
+
+  /// starts a new period every `PERIOD` ticks
+  pub fn step(&mut self, tick: u64) {
+    if tick > 0 && tick % PERIOD == 0 {
+      self.history.push(self.current);
+      self.current = Ledger::default();
+
+      if self.history.len() > HISTORY {
+        self.history.remove(0);
+      }
+    }
+  }
+
+  /// lines for the finance panel
+  pub fn report(&self, budget: Option<i64>) -> Vec<String> {
+    let mut lines = vec![match budget {
+      Some(budget) => format!("finances, {} left", budget),
+      None => "finances".to_owned(),
+    }];
+
+    let minutes = (PERIOD as f32 * TICK / 60.) as usize;
+
+    lines.push(format!(
+      "now: +{} -{} = {}",
+      self.current.income,
+      self.current.expenses,
+      self.current.profit()
+    ));
+
+    for (i, ledger) in self.history.iter().rev().enumerate() {
+      lines.push(format!(
+        "{} min ago: +{} -{} = {}",
+        (i + 1) * minutes,
+        ledger.income,
+        ledger.expenses,
+        ledger.profit()
+      ));
+    }
+
+    lines
+  }
+}
@@ -0,0 +1,161 @@
+use ggez::{
+  Context,
+  graphics::{self, DrawMode, Rect},
+  GameResult,
+};
+
+use cargo::Cargo;
+use path::Pos;
+use station::CATCHMENT;
+
+// industries take up this many cells in each direction
+pub const SIZE: i32 = 2;
+// most a factory turns into goods every second
+const PROCESSING: u32 = 10;
+
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq, Deserialize)]
+pub enum Kind {
+  Mine,
+  Farm,
+  Factory,
+  Port,
+}
+
+impl Kind {
+  pub const ALL: [Kind; 4] = [
+    Kind::Mine,
+    Kind::Farm,
+    Kind::Factory,
+    Kind::Port,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Kind::Mine => "mine",
+      Kind::Farm => "farm",
+      Kind::Factory => "factory",
+      Kind::Port => "port",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Kind> {
+    Kind::ALL.iter().find(|kind| kind.name() == name).cloned()
+  }
+
+  /// what comes out every second without anything going in
+  fn production(&self) -> Option<(Cargo, u32)> {
+    match self {
+      Kind::Mine => Some((Cargo::Coal, 4)),
+      Kind::Farm => Some((Cargo::Grain, 3)),
+      Kind::Factory | Kind::Port => None,
+    }
+  }
+
+  pub fn accepts(&self, cargo: Cargo) -> bool {
+    match (self, cargo) {
+      (Kind::Factory, Cargo::Coal) => true,
+      (Kind::Factory, Cargo::Grain) => true,
+      (Kind::Port, Cargo::Goods) => true,
+      (Kind::Port, Cargo::Coal) => true,
+      _ => false,
+    }
+  }
+
+  fn colour(&self) -> [f32; 4] {
+    match self {
+      Kind::Mine => [0.3, 0.3, 0.3, 0.8],
+      Kind::Farm => [0.9, 0.8, 0.2, 0.8],
+      Kind::Factory => [0.6, 0.2, 0.6, 0.8],
+      Kind::Port => [0.1, 0.5, 0.6, 0.8],
+    }
+  }
+}
+
+/// something that makes or wants cargo, sitting on a square of cells
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
+pub struct Industry {
+  pub kind: Kind,
+  // top left cell
+  pub cell: (i32, i32),
+  // delivered but not processed yet
+  stock: u32,
+}
+
+impl Industry {
+  pub fn new(kind: Kind, cell: (i32, i32)) -> Self {
+    Industry {
+      kind,
+      cell,
+      stock: 0,
+    }
+  }
+
+  /// the middle of the industry in the world
+  pub fn center(&self, cell_size: i16) -> Pos {
+    let cell = cell_size as i32;
+    Pos(self.cell.0 * cell + SIZE * cell / 2, self.cell.1 * cell + SIZE * cell / 2)
+  }
+
+  pub fn covers(&self, (x, y): (i32, i32)) -> bool {
+    x >= self.cell.0 && y >= self.cell.1 && x < self.cell.0 + SIZE && y < self.cell.1 + SIZE
+  }
+
+  /// whether a station at `pos` is close enough to serve it
+  pub fn serves(&self, pos: Pos, cell_size: i16) -> bool {
+    let (cx, cy) = self.center(cell_size).to_float();
+    let (x, y) = pos.to_float();
+
+    ((cx - x).powi(2) + (cy - y).powi(2)).sqrt() <= CATCHMENT * cell_size as f32
+  }
+
+  pub fn deliver(&mut self, amount: u32) {
+    self.stock += amount;
+  }
+
+  /// called once a second, returns what's ready to be picked up
+  pub fn produce(&mut self) -> Option<(Cargo, u32)> {
+    match self.kind {
+      Kind::Factory => {
+        let amount = self.stock.min(PROCESSING);
+        self.stock -= amount;
+
+        if amount > 0 { Some((Cargo::Goods, amount)) } else { None }
+      }
+      Kind::Port => {
+        // exports just disappear
+        self.stock = 0;
+        None
+      }
+      kind => kind.production(),
+    }
+  }
+
+  pub fn describe(&self) -> String {
+    let makes = match self.kind {
+      Kind::Factory => "goods".to_owned(),
+      kind => kind.production().map_or("nothing".to_owned(), |(cargo, _)| cargo.name().to_owned()),
+    };
+    let wants = Cargo::ALL.iter()
+        .filter(|cargo| self.kind.accepts(**cargo))
+        .map(|cargo| cargo.name())
+        .collect::<Vec<&str>>();
+
+    format!(
+      "{}: makes {}, wants {}",
+      self.kind.name(),
+      makes,
+      if wants.is_empty() { "nothing".to_owned() } else { wants.join(", ") }
+    )
+  }
+
+  pub fn draw(&self, ctx: &mut Context, cell_size: i16) -> GameResult<()> {
+    let cell = cell_size as f32;
+    let rect = Rect::new(self.cell.0 as f32 * cell, self.cell.1 as f32 * cell, SIZE as f32 * cell, SIZE as f32 * cell);
+
+    graphics::set_color(ctx, self.kind.colour().into())?;
+    graphics::rectangle(ctx, DrawMode::Fill, rect)?;
+
+    graphics::set_color(ctx, [0.0, 0.0, 0.0, 1.0].into())?;
+    graphics::rectangle(ctx, DrawMode::Line(2.), rect)
+  }
+}
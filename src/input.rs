@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use ggez::{
+  event::{Keycode, MouseButton},
+  GameResult,
+  GameError,
+};
+
+use tool::Tool;
+
+/// a key, mouse button or turn of the mouse wheel
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Input {
+  Key(Keycode),
+  Mouse(MouseButton),
+  // up or away from the player when true
+  Wheel(bool),
+}
+
+const WHEEL: [(&str, bool); 2] = [
+  ("WheelUp", true),
+  ("WheelDown", false),
+];
+
+const MOUSE: [(&str, MouseButton); 5] = [
+  ("MouseLeft", MouseButton::Left),
+  ("MouseMiddle", MouseButton::Middle),
+  ("MouseRight", MouseButton::Right),
+  ("MouseX1", MouseButton::X1),
+  ("MouseX2", MouseButton::X2),
+];
+
+impl Input {
+  /// keys go by their sdl names (`F1`, `Space`, `Return`, `.`), mouse buttons are `MouseLeft` and so on
+  /// and the wheel is `WheelUp` and `WheelDown`
+  pub fn from_name(name: &str) -> Option<Input> {
+    if let Some(&(_, up)) = WHEEL.iter().find(|&&(n, _)| n == name) {
+      return Some(Input::Wheel(up));
+    }
+
+    match MOUSE.iter().find(|&&(n, _)| n == name) {
+      Some(&(_, button)) => Some(Input::Mouse(button)),
+      None => Keycode::from_name(name).map(Input::Key),
+    }
+  }
+
+  pub fn name(&self) -> String {
+    match *self {
+      Input::Key(key) => key.name(),
+      Input::Mouse(button) => MOUSE.iter()
+          .find(|&&(_, b)| b == button)
+          .map_or("Mouse".to_owned(), |&(n, _)| n.to_owned()),
+      Input::Wheel(up) => if up { "WheelUp" } else { "WheelDown" }.to_owned(),
+    }
+  }
+}
+
+/// everything an input can be bound to
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+pub enum Action {
+  // use the current tool at the cursor
+  Use,
+  // drop the path or the current tool
+  Cancel,
+  // like cancel, then deselect and finally quit
+  Back,
+  // held while moving the mouse
+  Pan,
+  ZoomIn,
+  ZoomOut,
+  // switch to a tool
+  Pick(Tool),
+  // finish the path at the cursor
+  Build,
+  // switch between laying single and double track
+  Double,
+  // take back the last waypoint or delete the selected train
+  Delete,
+  // flip the new train or reverse the selected one
+  Reverse,
+  Stop,
+  // leave the selected train's cars past the ones it keeps standing
+  Uncouple,
+  // put an empty car on behind the selected train or take its last one off
+  AddCar,
+  RemoveCar,
+  // send the selected train back to a depot or call it off
+  Home,
+  // buy a train into the selected depot, pick its engine and put the picked one out
+  Buy,
+  Engine,
+  Release,
+  NextTrain,
+  Follow,
+  // show or hide the finance panel
+  Finances,
+  Pause,
+  Step,
+  Rate(usize),
+  // move through and change the fields of the train dialog, pick an industry, how many
+  // cars the selected train keeps or a train in the selected depot and its cars
+  Previous,
+  Next,
+  Decrease,
+  Increase,
+  // for taking a default binding away
+  Unbound,
+}
+
+pub struct Bindings {
+  map: HashMap<Input, Action>,
+}
+
+impl Bindings {
+  /// replaces the defaults with `overrides`, which map input names to actions
+  pub fn new(overrides: &HashMap<String, Action>) -> GameResult<Self> {
+    let mut bindings = Bindings::default();
+
+    for (name, &action) in overrides.iter() {
+      let input = Input::from_name(name)
+          .ok_or_else(|| GameError::ConfigError(format!("unknown key or button {}", name)))?;
+
+      bindings.map.insert(input, action);
+    }
+
+    Ok(bindings)
+  }
+
+  pub fn action(&self, input: Input) -> Option<Action> {
+    match self.map.get(&input) {
+      Some(&Action::Unbound) | None => None,
+      Some(&action) => Some(action),
+    }
+  }
+
+  /// the names of everything bound to `action`, for hints
+  pub fn name(&self, action: Action) -> String {
+    let mut names = self.map.iter()
+        .filter(|&(_, &a)| a == action)
+        .map(|(input, _)| input.name())
+        .collect::<Vec<String>>();
+
+    if names.is_empty() {
+      return "unbound".to_owned();
+    }
+
+    names.sort();
+    names.join("/")
+  }
+}
+
+impl Default for Bindings {
+  fn default() -> Self {
+    use self::Action::*;
+
+    let keys = [
+      (Keycode::Escape, Back),
+      (Keycode::Return, Build),
+      (Keycode::D, Double),
+      (Keycode::Backspace, Delete),
+      (Keycode::Delete, Delete),
+      (Keycode::Equals, ZoomIn),
+      (Keycode::Minus, ZoomOut),
+      (Keycode::F1, Pick(Tool::Select)),
+      (Keycode::F2, Pick(Tool::Track)),
+      (Keycode::F3, Pick(Tool::Demolish)),
+      (Keycode::F4, Pick(Tool::Signal)),
+      (Keycode::F5, Pick(Tool::Station)),
+      (Keycode::F6, Pick(Tool::Industry)),
+      (Keycode::F7, Pick(Tool::Train)),
+      (Keycode::F8, Pick(Tool::Limit)),
+      (Keycode::F9, Pick(Tool::Wires)),
+      (Keycode::F10, Pick(Tool::Depot)),
+      (Keycode::R, Reverse),
+      (Keycode::S, Stop),
+      (Keycode::U, Uncouple),
+      (Keycode::C, AddCar),
+      (Keycode::X, RemoveCar),
+      (Keycode::H, Home),
+      (Keycode::B, Buy),
+      (Keycode::E, Engine),
+      (Keycode::O, Release),
+      (Keycode::Tab, NextTrain),
+      (Keycode::F, Follow),
+      (Keycode::M, Finances),
+      (Keycode::Space, Pause),
+      (Keycode::Period, Step),
+      (Keycode::Num1, Rate(0)),
+      (Keycode::Num2, Rate(1)),
+      (Keycode::Num3, Rate(2)),
+      (Keycode::Num4, Rate(3)),
+      (Keycode::Up, Previous),
+      (Keycode::Down, Next),
+      (Keycode::Left, Decrease),
+      (Keycode::Right, Increase),
+    ];
+
+    let buttons = [
+      (MouseButton::Left, Use),
+      (MouseButton::Right, Cancel),
+      (MouseButton::Middle, Pan),
+    ];
+
+    let mut map = HashMap::new();
+    map.extend(keys.iter().map(|&(key, action)| (Input::Key(key), action)));
+    map.extend(buttons.iter().map(|&(button, action)| (Input::Mouse(button), action)));
+    map.insert(Input::Wheel(true), ZoomIn);
+    map.insert(Input::Wheel(false), ZoomOut);
+
+    Bindings {
+      map,
+    }
+  }
+}
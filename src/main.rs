@@ -3,59 +3,562 @@
 
 extern crate rand;
 extern crate ggez;
+extern crate ron;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
 
+mod buffer;
+mod camera;
+mod cargo;
+mod clock;
+mod config;
+mod crossing;
+mod depot;
+mod finance;
+mod industry;
+mod input;
+mod mapgen;
 mod path;
+mod replay;
+mod scenario;
+mod signal;
+mod station;
+mod stats;
+mod terrain;
+mod tool;
+mod town;
 mod train;
+mod ui;
+mod world;
 
 use ggez::{
-  event::{self, MouseState, MouseButton},
-  graphics::{self, Point2, DrawMode},
+  event::{self, MouseState, MouseButton, Keycode, Mod},
+  graphics::{self, Point2, DrawMode, Font, Rect},
+  timer::{
+    get_delta,
+    duration_to_f64,
+  },
   GameResult,
   Context,
 };
 
+use camera::Camera;
+use cargo::Cargo;
+use clock::Clock;
+use config::Config;
+use depot::Depot;
+use industry::{Industry, Kind};
+use input::{Action, Bindings, Input};
+use replay::Replay;
+use scenario::Scenario;
+use signal::Signal;
+use station::Station;
+use stats::Stats;
+use tool::Tool;
+
 use path::{
-  track::{
-    Track,
-    TrackPiece,
-  },
-  Path,
+  track::TrackPiece,
   Dir,
   Pos,
 };
 
-use train::Train;
-
-const GRID_SIZE: (i16, i16) = (40, 25);
-const GRID_CELL_SIZE: i16 = 32;
+use train::{
+  spawn::{self, SpawnConfig, Spawner},
+  traction::Traction,
+};
+use world::{
+  Command,
+  Event,
+  World,
+};
 
-const SCREEN_SIZE: (u32, u32) = (
-  GRID_SIZE.0 as u32 * GRID_CELL_SIZE as u32,
-  GRID_SIZE.1 as u32 * GRID_CELL_SIZE as u32,
-);
+// a minute of simulated time
+const HEADLESS_TICKS: u64 = 60 * 60;
+// the speed limits the limit tool goes through, from none down to the slowest
+const LIMITS: [f32; 4] = [150., 100., 60., 30.];
 
 struct GameState {
+  font: Font,
+  screen_size: (u32, u32),
   mouse_pos: Pos,
-  cam_pos: Pos,
-  path: Option<Path>,
-  tracks: Vec<Track>,
-  trains: Vec<Train>,
+  screen_pos: (i32, i32),
+  cursor_pos: Pos,
+  camera: Camera,
+  bindings: Bindings,
+  // the pan input is held down
+  panning: bool,
+  clock: Clock,
+  record: Replay,
+  playback: Option<Replay>,
+  tool: Tool,
+  // what the industry tool places
+  industry: Kind,
+  // whether the track tool lays two tracks at once
+  double: bool,
+  // the track piece under the cursor and how far along it is
+  hover: Option<(usize, f32)>,
+  spawner: Option<Spawner>,
+  selected: Option<usize>,
+  // how many cars the selected train keeps when it uncouples
+  keep: usize,
+  // the depot the depot tool has open, the train picked in it and what new ones get pulled
+  // by and how many cars they have
+  depot: Option<usize>,
+  picked: usize,
+  engine: Traction,
+  cars: usize,
+  follow: bool,
+  show_finances: bool,
+  world: World,
 }
 
 impl GameState {
-  pub fn new() -> Self {
-    GameState {
+  pub fn new(seed: u32, playback: Option<Replay>, config: &Config, bindings: Bindings) -> GameResult<Self> {
+    let world = config.world(seed);
+
+    Ok(GameState {
+      font: Font::default_font()?,
+      screen_size: config.screen_size(),
       mouse_pos: Pos(0, 0),
-      path: None,
-      tracks: Vec::new(),
-      trains: Vec::new(),
-      cam_pos: Pos(0, 0),
+      screen_pos: (0, 0),
+      cursor_pos: Pos(0, 0),
+      record: Replay::new(seed, config),
+      playback,
+      tool: Tool::Select,
+      industry: Kind::Mine,
+      double: false,
+      hover: None,
+      spawner: None,
+      selected: None,
+      keep: 1,
+      depot: None,
+      picked: 0,
+      engine: Traction::default(),
+      cars: SpawnConfig::default().cars,
+      follow: false,
+      show_finances: false,
+      world,
+      camera: Camera::new(config.screen_size()),
+      bindings,
+      panning: false,
+      clock: Clock::new(),
+    })
+  }
+
+  /// recomputes everything that depends on where the mouse points in the world
+  fn update_cursor(&mut self) {
+    self.cursor_pos = self.camera.to_world(self.screen_pos);
+
+    let snap = snap_to_grid(self.cursor_pos, self.world.grid.cell_size);
+
+    if snap == self.mouse_pos {
+      return;
+    }
+
+    self.mouse_pos = snap;
+
+    if let Some(ref mut path) = self.world.path {
+      path.add_path(snap);
+    }
+  }
+
+  /// records and applies a player action, unless a replay is driving the world
+  fn command(&mut self, cmd: Command) {
+    if self.playback.is_some() {
+      return;
+    }
+
+    self.record.record(self.world.tick, cmd);
+    self.world.apply(&cmd);
+  }
+
+  fn set_tool(&mut self, tool: Tool) {
+    if tool != Tool::Track && self.world.path.is_some() {
+      self.command(Command::CancelPath);
+    }
+
+    self.tool = tool;
+    self.spawner = if tool == Tool::Train { Some(Spawner::new()) } else { None };
+
+    if tool != Tool::Depot {
+      self.depot = None;
+    }
+  }
+
+  /// does whatever `action` means right now
+  fn act(&mut self, ctx: &mut Context, action: Action) {
+    match action {
+      Action::Use => self.use_tool(),
+      Action::Cancel => {
+        self.cancel();
+      }
+      Action::Back => {
+        if self.cancel() {
+          return;
+        }
+
+        if self.selected.is_some() {
+          self.selected = None;
+          self.follow = false;
+        } else {
+          ctx.quit().expect("Should never fail");
+        }
+      }
+      Action::Pan => self.panning = true,
+      Action::ZoomIn | Action::ZoomOut => {
+        self.camera.zoom_at(self.screen_pos, if action == Action::ZoomIn { 1 } else { -1 });
+        self.update_cursor();
+      }
+      Action::Pick(tool) => self.set_tool(tool),
+      Action::Build => {
+        if self.world.path.is_some() {
+          let to = self.mouse_pos;
+          self.command(Command::CommitPath(to));
+        }
+      }
+      Action::Double => self.double = !self.double,
+      Action::Delete => {
+        if self.world.path.is_some() {
+          self.command(Command::RemoveWaypoint);
+        } else if let (true, Some(selected)) = (self.spawner.is_none() && self.playback.is_none(), self.selected) {
+          self.command(Command::RemoveTrain(selected));
+          self.selected = None;
+          self.follow = false;
+        }
+      }
+      Action::Reverse => {
+        if let (false, Some(selected)) = (self.spawner.is_some(), self.selected) {
+          self.command(Command::ReverseTrain(selected));
+        } else if let Some(ref mut spawner) = self.spawner {
+          spawner.flip();
+        }
+      }
+      Action::Stop => {
+        if let (true, Some(selected)) = (self.spawner.is_none(), self.selected) {
+          self.command(Command::StopTrain(selected));
+        }
+      }
+      Action::Uncouple => {
+        if let (true, Some(selected)) = (self.spawner.is_none() && self.playback.is_none(), self.selected) {
+          let keep = self.keep;
+          self.command(Command::SplitTrain(selected, keep));
+        }
+      }
+      Action::AddCar | Action::RemoveCar => {
+        if let (true, Some(selected)) = (self.spawner.is_none() && self.playback.is_none(), self.selected) {
+          self.command(if action == Action::AddCar { Command::AddCar(selected) } else { Command::RemoveCar(selected) });
+        }
+      }
+      Action::Home => {
+        if let (true, Some(selected)) = (self.spawner.is_none(), self.selected) {
+          self.command(Command::SendToDepot(selected));
+        }
+      }
+      Action::Buy => {
+        if let Some(depot) = self.depot {
+          let mut config = SpawnConfig { traction: self.engine, cars: self.cars, ..SpawnConfig::default() };
+          config.speed = config.speed.min(self.engine.max_speed());
+
+          self.command(Command::BuyTrain(depot, config));
+        }
+      }
+      Action::Engine => {
+        let i = Traction::ALL.iter().position(|&traction| traction == self.engine).unwrap_or(0);
+        self.engine = Traction::ALL[(i + 1) % Traction::ALL.len()];
+      }
+      Action::Release => {
+        if let Some(depot) = self.depot {
+          let picked = self.picked;
+          self.command(Command::ReleaseTrain(depot, picked));
+        }
+      }
+      Action::NextTrain => {
+        // cycle through the trains and watch them
+        if !self.world.trains.is_empty() {
+          self.selected = Some(self.selected.map_or(0, |i| (i + 1) % self.world.trains.len()));
+          self.follow = true;
+        }
+      }
+      Action::Follow => {
+        if self.selected.is_some() {
+          self.follow = !self.follow;
+        }
+      }
+      Action::Finances => self.show_finances = !self.show_finances,
+      Action::Pause => self.clock.toggle_pause(),
+      Action::Step => self.clock.step(),
+      Action::Rate(rate) => self.clock.set_rate(rate),
+      Action::Previous | Action::Next if self.tool == Tool::Industry => {
+        let step = if action == Action::Next { 1 } else { Kind::ALL.len() - 1 };
+        let i = Kind::ALL.iter().position(|&kind| kind == self.industry).unwrap_or(0);
+
+        self.industry = Kind::ALL[(i + step) % Kind::ALL.len()];
+      }
+      Action::Previous | Action::Next | Action::Decrease | Action::Increase if self.depot.is_some() => {
+        let depot = self.depot.expect("checked above");
+        let stored = self.world.depots[depot].len();
+        let picked = self.picked;
+
+        match action {
+          Action::Previous => self.picked = (picked + stored.max(1) - 1) % stored.max(1),
+          Action::Next => self.picked = (picked + 1) % stored.max(1),
+          Action::Decrease => self.cars = (self.cars - 1).max(1),
+          _ => self.cars = (self.cars + 1).min(spawn::MAX_CARS),
+        }
+      }
+      Action::Decrease | Action::Increase if self.spawner.is_none() && self.selected.is_some() => {
+        let cars = self.selected.map_or(1, |selected| self.world.trains[selected].cars());
+        let keep = if action == Action::Increase { self.keep + 1 } else { self.keep.saturating_sub(1) };
+
+        // it has to keep one and leave one
+        self.keep = keep.min(cars.saturating_sub(1)).max(1);
+      }
+      Action::Previous | Action::Next | Action::Decrease | Action::Increase => {
+        if let Some(ref mut spawner) = self.spawner {
+          match action {
+            Action::Previous => spawner.select_field(-1),
+            Action::Next => spawner.select_field(1),
+            Action::Decrease => spawner.adjust(-1),
+            _ => spawner.adjust(1),
+          }
+        }
+      }
+      Action::Unbound => {}
+    }
+  }
+
+  /// drops the path or else the current tool, returns whether there was anything to drop
+  fn cancel(&mut self) -> bool {
+    if self.world.path.is_some() && self.playback.is_none() {
+      self.command(Command::CancelPath);
+      true
+    } else if self.tool != Tool::Select {
+      self.set_tool(Tool::Select);
+      true
+    } else {
+      false
+    }
+  }
+
+  fn release(&mut self, input: Input) {
+    if self.bindings.action(input) == Some(Action::Pan) {
+      self.panning = false;
+    }
+  }
+
+  /// the grid cell under the cursor
+  fn cursor_cell(&self) -> (i32, i32) {
+    let cell = self.world.grid.cell_size as f32;
+    let (x, y) = self.cursor_pos.to_float();
+
+    ((x / cell).floor() as i32, (y / cell).floor() as i32)
+  }
+
+  /// what's bound to `action`, for the hints
+  fn key(&self, action: Action) -> String {
+    self.bindings.name(action)
+  }
+
+  /// what a click does with the current tool
+  fn use_tool(&mut self) {
+    let cursor = self.cursor_pos;
+
+    match self.tool {
+      Tool::Select => {
+        self.selected = self.world.trains.iter().position(|train| train.hit(cursor));
+        self.follow = false;
+      }
+
+      Tool::Track => {
+        let to = self.mouse_pos;
+
+        // clicking the last waypoint again finishes the path
+        let cmd = self.world.path.as_ref().map(|path| if path.head_pos() == to {
+          Command::CommitPath(to)
+        } else {
+          Command::AddWaypoint(to)
+        });
+
+        if let Some(cmd) = cmd {
+          self.command(cmd);
+          return;
+        }
+
+        let Pos(x, y) = self.mouse_pos;
+        let Pos(mx, my) = cursor;
+
+        let is_x = x % self.world.grid.cell_size as i32 == 0;
+        let dir = if is_x {
+          if mx > x { Dir::Right } else { Dir::Left }
+        } else {
+          if my > y { Dir::Up } else { Dir::Down }
+        };
+        let double = self.double;
+
+        self.command(Command::StartPath(Pos(x, y), dir, double));
+      }
+
+      Tool::Demolish => {
+        // markers go before the track they sit on
+        let cmd = if let Some(signal) = self.world.pick_signal(cursor) {
+          Some(Command::RemoveSignal(signal))
+        } else if let Some(station) = self.world.pick_station(cursor) {
+          Some(Command::RemoveStation(station))
+        } else if let Some(depot) = self.world.pick_depot(cursor) {
+          Some(Command::RemoveDepot(depot))
+        } else if let Some((track, _)) = self.hover {
+          Some(Command::RemoveTrack(track))
+        } else {
+          self.world.pick_industry(cursor).map(Command::RemoveIndustry)
+        };
+
+        if let Some(cmd) = cmd {
+          self.command(cmd);
+        }
+      }
+
+      Tool::Signal => {
+        let cmd = match self.world.pick_signal(cursor) {
+          Some(signal) => Some(Command::ToggleSignal(signal)),
+          None => self.hover.map(|(track, dist)| Command::PlaceSignal(track, dist)),
+        };
+
+        if let Some(cmd) = cmd {
+          self.command(cmd);
+        }
+      }
+
+      Tool::Station => {
+        if self.world.pick_station(cursor).is_none() {
+          if let Some((track, dist)) = self.hover {
+            self.command(Command::PlaceStation(track, dist));
+          }
+        }
+      }
+
+      Tool::Industry => {
+        let kind = self.industry;
+        let cell = self.cursor_cell();
+        self.command(Command::PlaceIndustry(kind, cell));
+      }
+
+      Tool::Train => {
+        let cmd = self.spawner.as_ref().and_then(|spawner| spawner.spawn(&self.world.tracks, &self.world.electrified));
+        if let Some(cmd) = cmd {
+          self.command(cmd);
+        }
+      }
+
+      Tool::Limit => {
+        if let Some((track, _)) = self.hover {
+          let limit = match self.world.limits[track] {
+            None => Some(LIMITS[0]),
+            Some(limit) => LIMITS.iter().cloned().find(|&next| next < limit),
+          };
+
+          self.command(Command::SetLimit(track, limit));
+        }
+      }
+
+      Tool::Wires => {
+        if let Some((track, _)) = self.hover {
+          let on = !self.world.electrified[track];
+          self.command(Command::Electrify(track, on));
+        }
+      }
+
+      Tool::Depot => {
+        // clicking a depot opens it, clicking the end of a line puts one up there
+        if let Some(depot) = self.world.pick_depot(cursor) {
+          self.depot = Some(depot);
+          self.picked = 0;
+        } else if let Some((track, dist)) = self.hover {
+          let at_end = dist > self.world.tracks[track].len() / 2.;
+          self.command(Command::PlaceDepot(track, at_end));
+        }
+      }
+    }
+  }
+
+  /// marks what the current tool would act on
+  fn draw_tool(&self, ctx: &mut Context) -> GameResult<()> {
+    let cursor = self.cursor_pos;
+    let tracks = &self.world.tracks;
+
+    match self.tool {
+      Tool::Demolish => {
+        graphics::set_color(ctx, [0.9, 0.1, 0.1, 0.8].into())?;
+
+        if let Some(signal) = self.world.pick_signal(cursor) {
+          let pos = self.world.signals[signal].pos(tracks);
+          graphics::circle(ctx, DrawMode::Line(3.), pos.into(), 10., 0.2)
+        } else if let Some(station) = self.world.pick_station(cursor) {
+          let pos = self.world.stations[station].pos(tracks);
+          graphics::circle(ctx, DrawMode::Line(3.), pos.into(), 14., 0.2)
+        } else if let Some(depot) = self.world.pick_depot(cursor) {
+          let pos = self.world.depots[depot].pos(tracks);
+          graphics::circle(ctx, DrawMode::Line(3.), pos.into(), 14., 0.2)
+        } else if let Some((track, _)) = self.hover {
+          tracks[track].draw(ctx)
+        } else if let Some(industry) = self.world.pick_industry(cursor) {
+          let industry = &self.world.industries[industry];
+          let cell = self.world.grid.cell_size as f32;
+          let (x, y) = (industry.cell.0 as f32 * cell, industry.cell.1 as f32 * cell);
+          let size = industry::SIZE as f32 * cell;
+
+          graphics::rectangle(ctx, DrawMode::Line(3.), Rect::new(x, y, size, size))
+        } else {
+          Ok(())
+        }
+      }
+      Tool::Signal => match self.hover {
+        Some((track, dist)) if self.world.pick_signal(cursor).is_none() => Signal::new(track, dist).draw(ctx, tracks),
+        _ => Ok(()),
+      },
+      Tool::Station => match self.hover {
+        Some((track, dist)) if self.world.pick_station(cursor).is_none() => Station::new(track, dist).draw(ctx, tracks),
+        _ => Ok(()),
+      },
+      Tool::Industry => Industry::new(self.industry, self.cursor_cell()).draw(ctx, self.world.grid.cell_size),
+      Tool::Train => match self.spawner {
+        Some(ref spawner) => spawner.draw(ctx, tracks, &self.world.electrified),
+        None => Ok(()),
+      },
+      Tool::Limit => match self.hover {
+        Some((track, _)) => {
+          graphics::set_color(ctx, [0.9, 0.6, 0.1, 0.8].into())?;
+          tracks[track].draw(ctx)
+        }
+        None => Ok(()),
+      },
+      Tool::Wires => match self.hover {
+        Some((track, _)) => {
+          graphics::set_color(ctx, [0.2, 0.5, 0.9, 0.8].into())?;
+          tracks[track].draw(ctx)?;
+          tracks[track].draw_wires(ctx)
+        }
+        None => Ok(()),
+      },
+      Tool::Depot => match self.hover {
+        Some((track, dist)) if self.world.pick_depot(cursor).is_none() => {
+          let at_end = dist > tracks[track].len() / 2.;
+
+          if Depot::fits(tracks, track, at_end) {
+            Depot::new(track, at_end).draw(ctx, tracks)
+          } else {
+            Ok(())
+          }
+        }
+        _ => Ok(()),
+      },
+      Tool::Select | Tool::Track => Ok(()),
     }
   }
 }
 
-fn snap_to_grid(pos: Pos) -> Pos {
-  let gs = GRID_CELL_SIZE as f32;
+fn snap_to_grid(pos: Pos, cell_size: i16) -> Pos {
+  let gs = cell_size as f32;
   let pos = (pos.0 as f32, pos.1 as f32);
 
   // tile offset
@@ -77,8 +580,62 @@ fn snap_to_grid(pos: Pos) -> Pos {
 
 impl event::EventHandler for GameState {
   fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
-    for train in self.trains.iter_mut() {
-      train.update(ctx, &self.tracks);
+    let frame = duration_to_f64(get_delta(ctx)) as f32;
+
+    for _ in 0..self.clock.ticks(frame) {
+      if let Some(ref mut playback) = self.playback {
+        for cmd in playback.due(self.world.tick) {
+          self.world.apply(&cmd);
+        }
+      }
+
+      self.world.step();
+
+      // coupling and going into a depot take trains out of the list
+      for event in self.world.events.iter() {
+        match *event {
+          Event::Coupled(train, other) => {
+            self.selected = self.selected.map(|selected| {
+              if selected == other { train } else if selected > other { selected - 1 } else { selected }
+            });
+          }
+          Event::Stored(train) => {
+            if self.selected == Some(train) {
+              self.follow = false;
+            }
+
+            self.selected = match self.selected {
+              Some(selected) if selected == train => None,
+              Some(selected) if selected > train => Some(selected - 1),
+              selected => selected,
+            };
+          }
+          _ => {}
+        }
+      }
+    }
+
+    // the world might have changed under the cursor
+    self.hover = self.world.pick_track(self.cursor_pos);
+    if let Some(ref mut spawner) = self.spawner {
+      spawner.hover(self.hover);
+    }
+
+    // the replay might have removed the selected train or the open depot
+    if self.selected.map_or(false, |selected| selected >= self.world.trains.len()) {
+      self.selected = None;
+      self.follow = false;
+    }
+    if self.depot.map_or(false, |depot| depot >= self.world.depots.len()) {
+      self.depot = None;
+    }
+    if let Some(depot) = self.depot {
+      self.picked = self.picked.min(self.world.depots[depot].len().saturating_sub(1));
+    }
+
+    if let (true, Some(selected)) = (self.follow, self.selected) {
+      self.camera.follow(self.world.trains[selected].head_pos(), frame);
+      self.update_cursor();
     }
 
     Ok(())
@@ -86,43 +643,288 @@ impl event::EventHandler for GameState {
 
   fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
     graphics::clear(ctx);
-//    graphics::push_transform()
+    self.camera.apply(ctx)?;
+
+    let alpha = self.clock.alpha();
+
+    self.world.terrain.draw(ctx)?;
 
     // draw a grid
     graphics::set_color(ctx, [0.0, 0.0, 0.0, 0.6].into())?;
 
-    for i in 0..GRID_SIZE.0 {
-      let x: f32 = (i * GRID_CELL_SIZE) as f32;
-      let y: f32 = SCREEN_SIZE.1 as f32;
+    let grid = self.world.grid;
+    let (width, height) = grid.world_size();
+
+    for i in 0..grid.size.0 {
+      let x: f32 = (i * grid.cell_size) as f32;
+      let y: f32 = height as f32;
 
       graphics::line(ctx, &[Point2::new(x, 0.), Point2::new(x, y)], 1.)?;
     }
-    for i in 0..GRID_SIZE.0 {
-      let x: f32 = SCREEN_SIZE.0 as f32;
-      let y: f32 = (i * GRID_CELL_SIZE) as f32;
+    for i in 0..grid.size.1 {
+      let x: f32 = width as f32;
+      let y: f32 = (i * grid.cell_size) as f32;
 
       graphics::line(ctx, &[Point2::new(0., y), Point2::new(x, y)], 1.)?;
     }
 
+    for industry in self.world.industries.iter() {
+      industry.draw(ctx, self.world.grid.cell_size)?;
+    }
+
+    for town in self.world.towns.iter() {
+      town.draw(ctx, &self.font, self.world.grid.cell_size)?;
+    }
+
     // draw track
-    for track in self.tracks.iter() {
+    for track in self.world.tracks.iter() {
       track.draw(ctx)?;
     }
 
+    graphics::set_color(ctx, [0.2, 0.3, 0.6, 1.0].into())?;
+
+    for (track, &wired) in self.world.tracks.iter().zip(self.world.electrified.iter()) {
+      if wired {
+        track.draw_wires(ctx)?;
+      }
+    }
+
+    for crossing in self.world.crossings.iter() {
+      crossing.draw(ctx)?;
+    }
+
+    for buffer in self.world.buffers.iter() {
+      buffer.draw(ctx)?;
+    }
+
+    for depot in self.world.depots.iter() {
+      depot.draw(ctx, &self.world.tracks)?;
+    }
+
+    for (track, limit) in self.world.limits.iter().enumerate() {
+      if let Some(limit) = limit {
+        let track = &self.world.tracks[track];
+        ui::draw_sign(ctx, &self.font, track.at(track.len() / 2.).into(), &format!("{:.0}", limit))?;
+      }
+    }
+
+    // draw signals and stations
+    for station in self.world.stations.iter() {
+      station.draw(ctx, &self.world.tracks)?;
+    }
+
+    for signal in self.world.signals.iter() {
+      signal.draw(ctx, &self.world.tracks)?;
+    }
+
     // draw trains
-    for train in self.trains.iter_mut() {
-      train.draw(ctx)?;
+    if let Some(selected) = self.selected {
+      self.world.trains[selected].draw_highlight(ctx, alpha)?;
+    }
+
+    for train in self.world.trains.iter_mut() {
+      train.draw(ctx, alpha)?;
     }
 
     // draw the path
-    if let Some(ref path) = self.path {
-      path.draw(ctx)?;
+    if let Some(ref path) = self.world.path {
+      path.draw(ctx, self.world.can_afford(path.cost(&self.world.terrain)))?;
     }
 
+    // draw what the tool would do
+    self.draw_tool(ctx)?;
+
     // draw the mouse pos
     graphics::set_color(ctx, [1.0, 0.0, 1.0, 1.0].into())?;
     graphics::circle(ctx, DrawMode::Line(2.), Point2::new(self.mouse_pos.0 as f32, self.mouse_pos.1 as f32), 8., 0.1)?;
 
+    // overlays are drawn in screen space
+    self.camera.reset(ctx)?;
+
+    ui::draw_panel(ctx, &self.font, Point2::new(10., 10.), &[Tool::toolbar(self.tool, &self.bindings)])?;
+
+    let Pos(x, y) = self.cursor_pos;
+    let Pos(sx, sy) = self.mouse_pos;
+    let (cx, cy) = self.cursor_cell();
+
+    let mut status = vec![
+      format!(
+        "{} ({} pause, {} step, {}-{} rate)",
+        self.clock.label(),
+        self.key(Action::Pause),
+        self.key(Action::Step),
+        self.key(Action::Rate(0)),
+        self.key(Action::Rate(3))
+      ),
+      format!(
+        "cell {}, {}  cursor {}, {}  snap {}, {}",
+        cx,
+        cy,
+        x,
+        y,
+        sx,
+        sy
+      ),
+      format!("seed {}", self.record.seed),
+    ];
+    if let Some(budget) = self.world.budget {
+      status.push(format!("budget {} ({} finances)", budget, self.key(Action::Finances)));
+    }
+    if self.tool == Tool::Track {
+      status.push(format!(
+        "laying {} track ({} switch)",
+        if self.double { "double" } else { "single" },
+        self.key(Action::Double)
+      ));
+    }
+    if let (Tool::Limit, Some((track, _))) = (self.tool, self.hover) {
+      let show = |limit: Option<f32>| limit.map_or("none".to_owned(), |limit| format!("{:.0}", limit));
+
+      status.push(format!(
+        "{} #{}: limit {}, trains keep to {}",
+        self.world.tracks[track].name(),
+        track,
+        show(self.world.limits[track]),
+        show(self.world.speed_limit(track))
+      ));
+    }
+    if let (Tool::Wires, Some((track, _))) = (self.tool, self.hover) {
+      let piece = &self.world.tracks[track];
+
+      status.push(if self.world.electrified[track] {
+        format!("{} #{}: electrified, click to take the wires down", piece.name(), track)
+      } else {
+        format!("{} #{}: not electrified, wiring costs {}", piece.name(), track, piece.wiring_cost(&self.world.grid))
+      });
+    }
+    if let (Tool::Depot, Some((track, dist))) = (self.tool, self.hover) {
+      let piece = &self.world.tracks[track];
+
+      status.push(if Depot::fits(&self.world.tracks, track, dist > piece.len() / 2.) {
+        format!("{} #{}: a depot costs {}", piece.name(), track, world::DEPOT_COST)
+      } else {
+        format!("{} #{}: depots only go at the end of a line", piece.name(), track)
+      });
+    }
+    if self.tool == Tool::Industry {
+      status.push(format!(
+        "placing a {} ({}/{} change)",
+        self.industry.name(),
+        self.key(Action::Previous),
+        self.key(Action::Next)
+      ));
+    }
+    if let Some(i) = self.world.pick_town(self.cursor_pos) {
+      let town = &self.world.towns[i];
+      status.push(format!("{}: {} people", town.name, town.population));
+    }
+    if let Some(i) = self.world.pick_industry(self.cursor_pos) {
+      status.push(self.world.industries[i].describe());
+    }
+    if let Some(i) = self.world.pick_station(self.cursor_pos) {
+      let station = &self.world.stations[i];
+      let waiting = Cargo::ALL.iter()
+          .map(|cargo| format!("{} {}", station.waiting(*cargo), cargo.name()))
+          .collect::<Vec<String>>();
+
+      status.push(format!("station #{}: {} waiting", i, waiting.join(", ")));
+    }
+    if let Some(ref path) = self.world.path {
+      let cost = path.cost(&self.world.terrain);
+
+      if !path.has_route() {
+        status.push("no route to the cursor".to_owned());
+      } else if !self.world.can_afford(cost) {
+        status.push(format!("cost {}, can't afford it", cost));
+      } else {
+        status.push(format!("cost {}", cost));
+      }
+      status.push(format!(
+        "{} waypoint, again or {} build, {} undo, {} cancel",
+        self.key(Action::Use),
+        self.key(Action::Build),
+        self.key(Action::Delete),
+        self.key(Action::Cancel)
+      ));
+    }
+    if let Some(ref playback) = self.playback {
+      status.push(if playback.finished() { "replay finished" } else { "replaying" }.to_owned());
+    }
+
+    let height = status.len() as f32 * 18. + 22.;
+    ui::draw_panel(ctx, &self.font, Point2::new(10., self.screen_size.1 as f32 - height), &status)?;
+
+    if let Some(ref spawner) = self.spawner {
+      let hints = [
+        format!("{}/{} select, {}/{} change", self.key(Action::Previous), self.key(Action::Next), self.key(Action::Decrease), self.key(Action::Increase)),
+        format!("{} flip, {} place", self.key(Action::Reverse), self.key(Action::Use)),
+      ];
+
+      spawner.draw_dialog(ctx, &self.font, Point2::new(10., 46.), &hints)?;
+    }
+
+    if let Some(depot) = self.depot {
+      let mut lines = self.world.depots[depot].inspect(self.picked);
+      lines.push(format!(
+        "{}/{} pick, {}/{} fewer/more cars",
+        self.key(Action::Previous),
+        self.key(Action::Next),
+        self.key(Action::Decrease),
+        self.key(Action::Increase)
+      ));
+      lines.push(format!(
+        "{} buy {} with {} cars ({}), {} change engine",
+        self.key(Action::Buy),
+        self.engine.name(),
+        self.cars,
+        self.engine.price(),
+        self.key(Action::Engine)
+      ));
+      lines.push(format!("{} put out", self.key(Action::Release)));
+
+      ui::draw_panel(ctx, &self.font, Point2::new(10., 46.), &lines)?;
+    }
+
+    if let Some(selected) = self.selected {
+      let mut lines = vec![format!("train #{}", selected)];
+      lines.append(&mut self.world.trains[selected].inspect(&self.world.tracks, &self.world.terrain));
+      lines.push(format!(
+        "{} stop/start, {} reverse, {} delete",
+        self.key(Action::Stop),
+        self.key(Action::Reverse),
+        self.key(Action::Delete)
+      ));
+      lines.push(format!(
+        "{} follow ({}), {} next train",
+        self.key(Action::Follow),
+        if self.follow { "on" } else { "off" },
+        self.key(Action::NextTrain)
+      ));
+      lines.push(format!(
+        "{}/{} keep {} cars, {} uncouple",
+        self.key(Action::Decrease),
+        self.key(Action::Increase),
+        self.keep,
+        self.key(Action::Uncouple)
+      ));
+      lines.push(format!("{}/{} add/take off a car", self.key(Action::AddCar), self.key(Action::RemoveCar)));
+      lines.push(if self.world.depot_on_line(selected) {
+        format!("{} back to a depot", self.key(Action::Home))
+      } else {
+        "no depot on this line to go back to".to_owned()
+      });
+
+      ui::draw_panel(ctx, &self.font, Point2::new(self.screen_size.0 as f32 - 280., 10.), &lines)?;
+    }
+
+    if self.show_finances {
+      let lines = self.world.finances.report(self.world.budget);
+      let height = lines.len() as f32 * 18. + 22.;
+      let pos = Point2::new(self.screen_size.0 as f32 - 300., self.screen_size.1 as f32 - height);
+
+      ui::draw_panel(ctx, &self.font, pos, &lines)?;
+    }
+
     // finish up
     graphics::present(ctx);
     ggez::timer::yield_now();
@@ -132,83 +934,182 @@ impl event::EventHandler for GameState {
 
   fn mouse_button_down_event(
     &mut self,
-    _ctx: &mut Context,
+    ctx: &mut Context,
     button: MouseButton,
-    mx: i32,
-    my: i32,
+    _mx: i32,
+    _my: i32,
   ) {
-    let Pos(x, y) = self.mouse_pos;
-
-    match button {
-      MouseButton::Left => {
-        if self.path.is_none() {
-          let is_x = x % GRID_CELL_SIZE as i32 == 0;
-          self.path = Some(Path::new(Pos(x, y), if is_x {
-            if mx > x { Dir::Right } else { Dir::Left }
-          } else {
-            if my > y { Dir::Up } else { Dir::Down }
-          }));
-          return;
-        }
-
-        let mut path = None;
-        std::mem::swap(&mut self.path, &mut path);
-        let mut path = path.expect("we checked for none");
-
-        if let Some(mut pieces) = path.into_pieces() {
-          self.tracks.append(&mut pieces);
-        }
-      }
-
-      MouseButton::Right => {
-        // add train
-
-        self.trains.push(Train::new(200., 0, 0., (4, 10., 40.)));
-      }
+    if let Some(action) = self.bindings.action(Input::Mouse(button)) {
+      self.act(ctx, action);
+    }
+  }
 
-      _ => {}
-    };
+  fn mouse_button_up_event(
+    &mut self,
+    _ctx: &mut Context,
+    button: MouseButton,
+    _mx: i32,
+    _my: i32,
+  ) {
+    self.release(Input::Mouse(button));
   }
 
   fn mouse_motion_event(
     &mut self,
     _ctx: &mut Context,
-    state: MouseState,
+    _state: MouseState,
     x: i32,
     y: i32,
     dx: i32,
     dy: i32,
   ) {
-    if state.middle() {
-      self.cam_pos.0 += dx;
-      self.cam_pos.1 += dy;
+    if self.panning {
+      self.camera.pan(dx, dy);
+      self.follow = false;
     }
 
-    let Pos(cx, cy) = self.cam_pos;
+    self.screen_pos = (x, y);
+    self.update_cursor();
+  }
 
-    let snap = snap_to_grid(Pos(x + cx, y + cy));
+  fn mouse_wheel_event(&mut self, ctx: &mut Context, _x: i32, y: i32) {
+    // every notch the wheel turns counts as a press and letting go again
+    let input = Input::Wheel(y > 0);
 
-    if snap == self.mouse_pos {
-      return;
+    if let Some(action) = self.bindings.action(input) {
+      for _ in 0..y.abs() {
+        self.act(ctx, action);
+      }
     }
+    self.release(input);
+  }
 
-    self.mouse_pos = snap;
-
-    if let Some(ref mut path) = self.path {
-      path.add_path(snap);
+  fn key_down_event(&mut self, ctx: &mut Context, keycode: Keycode, _keymod: Mod, _repeat: bool) {
+    if let Some(action) = self.bindings.action(Input::Key(keycode)) {
+      self.act(ctx, action);
     }
   }
+
+  fn key_up_event(&mut self, _ctx: &mut Context, keycode: Keycode, _keymod: Mod, _repeat: bool) {
+    self.release(Input::Key(keycode));
+  }
+}
+
+/// the value following `name` on the command line
+fn arg(name: &str) -> Option<String> {
+  std::env::args().skip_while(|arg| arg != name).nth(1)
+}
+
+/// whether `name` is on the command line
+fn flag(name: &str) -> bool {
+  std::env::args().any(|arg| arg == name)
+}
+
+/// plays a recorded run back without a window and prints what happened and how it ended up
+fn run_replay(path: &str, out: Option<String>, config: &Config) -> GameResult<()> {
+  let mut replay = Replay::load(path)?;
+  let mut world = replay.configure(config).world(replay.seed);
+  let mut stats = Stats::new();
+
+  replay.play(&mut world, &mut stats);
+
+  print!("{}{}", stats.report(), replay::summary(&world));
+
+  if let Some(out) = out {
+    stats.save(&out)?;
+  }
+
+  Ok(())
+}
+
+/// runs a scenario without a window and prints what happened
+fn run_headless(scenario: &str, ticks: u64, out: Option<String>, config: &Config) -> GameResult<()> {
+  let mut world = Scenario::load(scenario)?.build(config.grid)?;
+  let mut stats = Stats::new();
+
+  for _ in 0..ticks {
+    world.step();
+    stats.observe(&world);
+  }
+
+  print!("{}", stats.report());
+
+  if let Some(out) = out {
+    stats.save(&out)?;
+  }
+
+  Ok(())
 }
 
 fn main() {
+  let config = match Config::from_args() {
+    Ok(config) => config,
+    Err(e) => {
+      println!("Error encountered reading the settings: {}", e);
+      std::process::exit(1);
+    }
+  };
+
+  if let Some(scenario) = arg("--headless") {
+    let ticks = arg("--ticks").and_then(|ticks| ticks.parse().ok()).unwrap_or(HEADLESS_TICKS);
+
+    if let Err(e) = run_headless(&scenario, ticks, arg("--stats"), &config) {
+      println!("Error encountered running scenario: {}", e);
+      std::process::exit(1);
+    }
+
+    return;
+  }
+
+  if let Some(path) = arg("--headless-replay") {
+    if let Err(e) = run_replay(&path, arg("--stats"), &config) {
+      println!("Error encountered playing the replay: {}", e);
+      std::process::exit(1);
+    }
+
+    return;
+  }
+
+  let playback = match arg("--replay").map(|path| Replay::load(&path)) {
+    Some(Ok(playback)) => Some(playback),
+    Some(Err(e)) => {
+      println!("Error encountered loading the replay: {}", e);
+      std::process::exit(1);
+    }
+    None => None,
+  };
+
+  // a replay only plays out the same on the world it was recorded on
+  let config = match playback {
+    Some(ref playback) => playback.configure(&config),
+    None => config,
+  };
+
+  let bindings = match config.bindings() {
+    Ok(bindings) => bindings,
+    Err(e) => {
+      println!("Error encountered reading the key bindings: {}", e);
+      std::process::exit(1);
+    }
+  };
+
+  let (width, height) = config.screen_size();
+
   let ctx = &mut ggez::ContextBuilder::new("train_thing", "Leah")
       .window_setup(ggez::conf::WindowSetup::default().title("Trains!"))
-      .window_mode(ggez::conf::WindowMode::default().dimensions(SCREEN_SIZE.0, SCREEN_SIZE.1))
+      .window_mode(ggez::conf::WindowMode::default().dimensions(width, height))
       .build().expect("Failed to build ggez context");
 
   graphics::set_background_color(ctx, [1.0, 1.0, 1.0, 1.0].into());
 
-  let state = &mut GameState::new();
+  // every new game gets its own map, the seed is shown and recorded so it can be had again
+  let seed = match playback {
+    Some(ref playback) => playback.seed,
+    None => arg("--seed").and_then(|seed| seed.parse().ok()).unwrap_or_else(rand::random),
+  };
+  println!("Starting from seed {}", seed);
+
+  let state = &mut GameState::new(seed, playback, &config, bindings).expect("Failed to load the default font");
 
   match event::run(ctx, state) {
     // If we encounter an error, we print it before exiting
@@ -216,4 +1117,13 @@ fn main() {
     // And if not, we print a message saying we ran cleanly. Hooray!
     Ok(_) => println!("Game exited cleanly!")
   }
+
+  if let Some(path) = arg("--record") {
+    state.record.ticks = state.world.tick;
+
+    match state.record.save(&path) {
+      Err(e) => println!("Failed to save the replay: {}", e),
+      Ok(_) => println!("Replay saved to {}", path),
+    }
+  }
 }
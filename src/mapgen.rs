@@ -0,0 +1,181 @@
+use rand::Rng;
+
+use industry::{self, Kind};
+use terrain::Ground;
+use world::{
+  Command,
+  World,
+};
+
+// cells between the points the terrain noise is made of, coarse then fine
+const OCTAVES: [(i32, f32); 2] = [(8, 0.7), (3, 0.3)];
+// noise below this is water, above the other one hills
+const WATER: f32 = 0.3;
+const HILLS: f32 = 0.7;
+// how many pixels up the land goes for the whole range of the noise
+const RELIEF: f32 = 20.;
+// one town and one industry for every this many cells
+const CELLS_PER_TOWN: i32 = 160;
+const CELLS_PER_INDUSTRY: i32 = 120;
+// towns don't start closer to each other than this many cells
+const TOWN_SPACING: i32 = 8;
+const TOWN_POPULATION: (u32, u32) = (150, 600);
+// attempts at finding a spot for each town or industry
+const TRIES: usize = 50;
+
+const NAME_STARTS: [&str; 12] = [
+  "Ash", "Brook", "Carr", "Dun", "Elm", "Fair", "Glen", "Hart", "Kings", "Lang", "Mill", "North",
+];
+const NAME_ENDS: [&str; 8] = [
+  "by", "ford", "ham", "ley", "ton", "wick", "bridge", "field",
+];
+
+/// fills an empty world with terrain, towns and industries, all from the world's rng
+/// so the same seed always makes the same map
+pub fn generate(world: &mut World) {
+  terrain(world);
+  towns(world);
+  industries(world);
+}
+
+fn terrain(world: &mut World) {
+  let (w, h) = (world.grid.size.0 as i32, world.grid.size.1 as i32);
+  // sampled at the nodes, each cell goes by the one at its top left corner
+  let (nw, nh) = (w + 1, h + 1);
+  let mut height = vec![0f32; (nw * nh) as usize];
+
+  for &(spacing, weight) in OCTAVES.iter() {
+    let (gw, gh) = (w / spacing + 2, h / spacing + 2);
+    let points = (0..gw * gh).map(|_| world.rng.gen_range(0., 1.)).collect::<Vec<f32>>();
+
+    for y in 0..nh {
+      for x in 0..nw {
+        let (fx, fy) = (x as f32 / spacing as f32, y as f32 / spacing as f32);
+        let (ix, iy) = (fx as i32, fy as i32);
+        let (tx, ty) = (smooth(fx.fract()), smooth(fy.fract()));
+
+        let at = |dx: i32, dy: i32| points[((iy + dy) * gw + ix + dx) as usize];
+        let top = at(0, 0) + (at(1, 0) - at(0, 0)) * tx;
+        let bottom = at(0, 1) + (at(1, 1) - at(0, 1)) * tx;
+
+        height[(y * nw + x) as usize] += (top + (bottom - top) * ty) * weight;
+      }
+    }
+  }
+
+  for (i, &height) in height.iter().enumerate() {
+    let node = (i as i32 % nw, i as i32 / nw);
+
+    // the water is all at the same level and the land rises from its shore
+    world.terrain.set_height(node, (height - WATER).max(0.) * RELIEF);
+
+    if height < WATER {
+      world.terrain.set(node, Ground::Water);
+    } else if height > HILLS {
+      world.terrain.set(node, Ground::Hills);
+    }
+  }
+}
+
+/// eases the interpolation so the noise doesn't have creases
+fn smooth(t: f32) -> f32 {
+  t * t * (3. - 2. * t)
+}
+
+fn towns(world: &mut World) {
+  let (w, h) = (world.grid.size.0 as i32, world.grid.size.1 as i32);
+  let count = (w * h / CELLS_PER_TOWN).max(1);
+
+  // towns keep off the edge of the map, a tiny one is all edge
+  if w < 3 || h < 3 {
+    return;
+  }
+
+  for _ in 0..count {
+    for _ in 0..TRIES {
+      let cell = (world.rng.gen_range(1, w - 1), world.rng.gen_range(1, h - 1));
+
+      let spaced = world.towns.iter().all(|town| {
+        let (x, y) = town.buildings()[0];
+        (x - cell.0).abs().max((y - cell.1).abs()) >= TOWN_SPACING
+      });
+
+      if world.terrain.ground(cell) != Ground::Grass || !spaced {
+        continue;
+      }
+
+      let name = name(world);
+      let population = world.rng.gen_range(TOWN_POPULATION.0, TOWN_POPULATION.1);
+
+      world.found_town(name, cell, population);
+      break;
+    }
+  }
+}
+
+/// a made up name no other town has yet, if one turns up
+fn name(world: &mut World) -> String {
+  let mut name = String::new();
+
+  for _ in 0..TRIES {
+    name = format!(
+      "{}{}",
+      NAME_STARTS[world.rng.gen_range(0, NAME_STARTS.len())],
+      NAME_ENDS[world.rng.gen_range(0, NAME_ENDS.len())]
+    );
+
+    if world.towns.iter().all(|town| town.name != name) {
+      break;
+    }
+  }
+
+  name
+}
+
+fn industries(world: &mut World) {
+  let (w, h) = (world.grid.size.0 as i32, world.grid.size.1 as i32);
+  let count = (w * h / CELLS_PER_INDUSTRY).max(Kind::ALL.len() as i32);
+
+  // a map smaller than an industry has no room for any
+  if w <= industry::SIZE || h <= industry::SIZE {
+    return;
+  }
+
+  for i in 0..count {
+    // one of each first so every chain can be completed
+    let kind = Kind::ALL[i as usize % Kind::ALL.len()];
+
+    for _ in 0..TRIES {
+      let cell = (world.rng.gen_range(0, w - industry::SIZE), world.rng.gen_range(0, h - industry::SIZE));
+
+      if suits(world, kind, cell) {
+        world.apply(&Command::PlaceIndustry(kind, cell));
+        break;
+      }
+    }
+  }
+}
+
+/// whether an industry of `kind` can go with its top left at `cell`
+fn suits(world: &World, kind: Kind, cell: (i32, i32)) -> bool {
+  let covered = (0..industry::SIZE * industry::SIZE)
+      .map(|i| (cell.0 + i % industry::SIZE, cell.1 + i / industry::SIZE))
+      .collect::<Vec<(i32, i32)>>();
+
+  let clear = covered.iter().all(|&spot| {
+    world.terrain.ground(spot) != Ground::Water
+        && world.towns.iter().all(|town| !town.covers(spot))
+        && world.industries.iter().all(|industry| !industry.covers(spot))
+  });
+
+  // a ring around the industry, for the things it wants next to it
+  let around = (-1..industry::SIZE + 1)
+      .flat_map(|dx| (-1..industry::SIZE + 1).map(move |dy| (cell.0 + dx, cell.1 + dy)))
+      .collect::<Vec<(i32, i32)>>();
+
+  clear && match kind {
+    Kind::Mine => covered.iter().any(|&spot| world.terrain.ground(spot) == Ground::Hills),
+    Kind::Port => around.iter().any(|&spot| world.terrain.ground(spot) == Ground::Water),
+    Kind::Farm | Kind::Factory => covered.iter().all(|&spot| world.terrain.ground(spot) == Ground::Grass),
+  }
+}
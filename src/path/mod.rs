@@ -7,21 +7,25 @@ use ggez::{
 };
 
 use std::{
-  collections::HashMap,
+  collections::{HashMap, HashSet},
   ops::{
     Add,
     Sub,
   },
 };
 
-use super::{
-  GRID_CELL_SIZE,
-  SCREEN_SIZE,
-};
+use config::Grid;
+use terrain::{Ground, Terrain};
 
-use self::track::{TrackPiece, Track, TURN_LEN, DIAG_LEN, STRT_LEN};
+use self::track::{Bridge, Diagonal, TrackPiece, Track, Tunnel, TURN_ANGLE};
 
-#[derive(Debug, Copy, Clone, PartialOrd, PartialEq, Ord, Eq, Hash)]
+// the longest bridge and tunnel that can be built, in cells
+const MAX_BRIDGE: f32 = 8.;
+const MAX_TUNNEL: f32 = 12.;
+// how many pieces back a bend can keep the second track from getting round the next one
+const BEHIND: usize = 3;
+
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq, Ord, Eq, Hash, Deserialize)]
 pub struct Pos(pub i32, pub i32);
 
 impl Pos {
@@ -81,7 +85,7 @@ impl Sub for Pos {
   }
 }
 
-#[derive(Debug, Copy, Clone, PartialOrd, PartialEq, Ord, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq, Ord, Eq, Hash, Deserialize)]
 pub enum Dir {
   Up,
   UpRight,
@@ -94,21 +98,19 @@ pub enum Dir {
 }
 
 impl Dir {
+  /// a single step in this direction
   fn to_pos(&self) -> Pos {
     use self::Dir::*;
 
-    let diag = (2f32.sqrt() * GRID_CELL_SIZE as f32) as i32;
-    let strt = GRID_CELL_SIZE as i32;
-
     match &self {
-      Up => Pos(0, strt),
-      UpRight => Pos(diag, diag),
-      Right => Pos(strt, 0),
-      DownRight => Pos(diag, -diag),
-      Down => Pos(0, -strt),
-      DownLeft => Pos(-diag, -diag),
-      Left => Pos(-strt, 0),
-      UpLeft => Pos(-diag, diag),
+      Up => Pos(0, 1),
+      UpRight => Pos(1, 1),
+      Right => Pos(1, 0),
+      DownRight => Pos(1, -1),
+      Down => Pos(0, -1),
+      DownLeft => Pos(-1, -1),
+      Left => Pos(-1, 0),
+      UpLeft => Pos(-1, 1),
     }
   }
 
@@ -126,6 +128,28 @@ impl Dir {
       UpLeft => DownRight,
     }
   }
+  pub fn name(&self) -> &'static str {
+    use self::Dir::*;
+
+    match self {
+      Up => "up",
+      UpRight => "up_right",
+      Right => "right",
+      DownRight => "down_right",
+      Down => "down",
+      DownLeft => "down_left",
+      Left => "left",
+      UpLeft => "up_left",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Dir> {
+    use self::Dir::*;
+
+    [Up, UpRight, Right, DownRight, Down, DownLeft, Left, UpLeft].iter()
+        .find(|dir| dir.name() == name)
+        .cloned()
+  }
 }
 
 #[derive(Debug, Copy, Clone, PartialOrd, PartialEq, Ord, Eq, Hash)]
@@ -142,13 +166,30 @@ impl Connection {
     }
   }
 
-  fn gen_connections(&self) -> Vec<(Connection, i32)> {
+  /// the connection on the next line over to the right, facing the same way, lines along the
+  /// grid are a cell apart and diagonals half a cell each way so they stay where connections can be
+  fn shifted(&self, cell: i32) -> Connection {
+    let Pos(dx, dy) = self.dir.to_pos();
+    let by = if dx != 0 && dy != 0 { cell / 2 } else { cell };
+
+    Connection::new(self.pos + Pos(dy * by, -dx * by), self.dir)
+  }
+
+  fn gen_connections(&self, grid: &Grid) -> Vec<(Connection, i32)> {
     let start = *self;
 
-    let gs = GRID_CELL_SIZE as f32;
+    let gs = grid.cell_size as f32;
     let (x, y) = start.pos.to_float();
 
-    let is_x = x % gs == 0.;
+    // a turn goes `along` in its starting direction and `side` to the side
+    let radius = grid.turn_radius * gs;
+    let along = 0.6 * radius;
+    let side = 0.2 * radius;
+
+    // which turn a diagonal can take depends on which kind of edge it's on,
+    // that flips with every 2.5 cells of turn radius
+    let odd = (grid.turn_radius / 2.5) as i32 % 2 == 1;
+    let is_x = (x % gs == 0.) == odd;
 
     use self::Dir::*;
 
@@ -156,68 +197,70 @@ impl Connection {
       Connection::new(Pos(pos.0 as i32, pos.1 as i32), dir)
     };
 
-    let turn = TURN_LEN as i32;
-    let strt = STRT_LEN as i32;
-    let diag = DIAG_LEN as i32;
+    let turn = (TURN_ANGLE * radius) as i32;
+    let strt = gs as i32;
+    let diag = (2f32.sqrt() * 0.5 * gs) as i32;
 
     let conns = match start.dir {
       Right => vec![
-        (conn((x + 1.5 * gs, y - 0.5 * gs), DownRight), turn),
+        (conn((x + along, y - side), DownRight), turn),
         (conn((x + 1. * gs, y), Right), strt),
-        (conn((x + 1.5 * gs, y + 0.5 * gs), UpRight), turn),
+        (conn((x + along, y + side), UpRight), turn),
       ],
       UpRight => vec![
         (conn((x + 0.5 * gs, y + 0.5 * gs), UpRight), diag),
         if is_x {
-          (conn((x + 0.5 * gs, y + 1.5 * gs), Up), turn)
+          (conn((x + side, y + along), Up), turn)
         } else {
-          (conn((x + 1.5 * gs, y + 0.5 * gs), Right), turn)
+          (conn((x + along, y + side), Right), turn)
         },
       ],
       DownRight => vec![
         (conn((x + 0.5 * gs, y - 0.5 * gs), DownRight), diag),
         if is_x {
-          (conn((x + 0.5 * gs, y - 1.5 * gs), Down), turn)
+          (conn((x + side, y - along), Down), turn)
         } else {
-          (conn((x + 1.5 * gs, y - 0.5 * gs), Right), turn)
+          (conn((x + along, y - side), Right), turn)
         },
       ],
       Up => vec![
-        (conn((x + 0.5 * gs, y + 1.5 * gs), UpRight), turn),
+        (conn((x + side, y + along), UpRight), turn),
         (conn((x, y + 1. * gs), Up), strt),
-        (conn((x - 0.5 * gs, y + 1.5 * gs), UpLeft), turn),
+        (conn((x - side, y + along), UpLeft), turn),
       ],
       Down => vec![
-        (conn((x - 0.5 * gs, y - 1.5 * gs), DownLeft), turn),
+        (conn((x - side, y - along), DownLeft), turn),
         (conn((x, y - 1. * gs), Down), strt),
-        (conn((x + 0.5 * gs, y - 1.5 * gs), DownRight), turn),
+        (conn((x + side, y - along), DownRight), turn),
       ],
       Left => vec![
-        (conn((x - 1.5 * gs, y + 0.5 * gs), UpLeft), turn),
+        (conn((x - along, y + side), UpLeft), turn),
         (conn((x - 1. * gs, y), Left), strt),
-        (conn((x - 1.5 * gs, y - 0.5 * gs), DownLeft), turn),
+        (conn((x - along, y - side), DownLeft), turn),
       ],
       UpLeft => vec![
         (conn((x - 0.5 * gs, y + 0.5 * gs), UpLeft), diag),
         if is_x {
-          (conn((x - 0.5 * gs, y + 1.5 * gs), Up), turn)
+          (conn((x - side, y + along), Up), turn)
         } else {
-          (conn((x - 1.5 * gs, y + 0.5 * gs), Left), turn)
+          (conn((x - along, y + side), Left), turn)
         },
       ],
       DownLeft => vec![
         (conn((x - 0.5 * gs, y - 0.5 * gs), DownLeft), diag),
         if is_x {
-          (conn((x - 0.5 * gs, y - 1.5 * gs), Down), turn)
+          (conn((x - side, y - along), Down), turn)
         } else {
-          (conn((x - 1.5 * gs, y - 0.5 * gs), Left), turn)
+          (conn((x - along, y - side), Left), turn)
         },
       ],
     };
 
+    let (w, h) = grid.world_size();
+
     conns.into_iter().filter(|(p, _)| {
       let Pos(x, y) = p.pos;
-      x > 0 && x < SCREEN_SIZE.0 as i32 && y > 0 && y < SCREEN_SIZE.1 as i32
+      x > 0 && x < w && y > 0 && y < h
     }).collect()
   }
 }
@@ -226,24 +269,208 @@ impl Connection {
 // #[derive(Debug, Clone, PartialOrd, PartialEq)]
 pub struct Path {
   start: Connection,
+  grid: Grid,
+  // what decides where bridges and tunnels go and how dear each piece is
+  terrain: Terrain,
+  // cells the path has to go around
+  blocked: HashSet<(i32, i32)>,
+  // cells track already runs through, the second track has to keep off them
+  taken: HashSet<(i32, i32)>,
+  // lays a second track a cell to the side of every piece
+  double: bool,
+  // the legs up to the last waypoint
+  pieces: Vec<Track>,
+  // how many pieces each leg added
+  legs: Vec<usize>,
+  // the route from the last waypoint to `target`
   path: Option<Vec<Track>>,
+  target: Option<Pos>,
 }
 
 impl Path {
-  pub fn new(start: Pos, dir: Dir) -> Self {
+  pub fn new(start: Pos, dir: Dir, grid: Grid, terrain: Terrain, blocked: HashSet<(i32, i32)>, taken: HashSet<(i32, i32)>, double: bool) -> Self {
     Path {
       start: Connection::new(start, dir),
+      grid,
+      terrain,
+      blocked,
+      taken,
+      double,
+      pieces: Vec::new(),
+      legs: Vec::new(),
       path: None,
+      target: None,
+    }
+  }
+
+  /// where the next leg starts
+  fn head(&self) -> Connection {
+    self.pieces.last().map_or(self.start, |piece| piece.end())
+  }
+
+  pub fn head_pos(&self) -> Pos {
+    self.head().pos
+  }
+
+  /// where the first piece starts
+  pub fn start(&self) -> Connection {
+    self.start
+  }
+
+  /// whether there's a route to the cursor
+  pub fn has_route(&self) -> bool {
+    self.path.is_some()
+  }
+
+  /// fixes the route to `to` as a leg, returns false if there isn't one
+  pub fn add_waypoint(&mut self, to: Pos) -> bool {
+    self.add_path(to);
+
+    match self.path.take() {
+      Some(mut leg) => {
+        self.legs.push(leg.len());
+        self.pieces.append(&mut leg);
+        self.add_path(to);
+        true
+      }
+      None => false,
     }
   }
 
-  pub fn into_pieces(self) -> Option<Vec<Track>> {
-    self.path
+  /// drops the last leg, returns false if there wasn't one
+  pub fn remove_waypoint(&mut self) -> bool {
+    match self.legs.pop() {
+      Some(len) => {
+        let keep = self.pieces.len() - len;
+        self.pieces.truncate(keep);
+
+        if let Some(target) = self.target {
+          self.add_path(target);
+        }
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// what building everything up to the cursor would cost
+  pub fn cost(&self, terrain: &Terrain) -> i64 {
+    self.pieces.iter()
+        .chain(self.path.iter().flat_map(|path| path.iter()))
+        .chain(self.twins().iter())
+        .map(|piece| piece.cost(&self.grid, terrain))
+        .sum()
+  }
+
+  /// the second track of a double path, a line over from everything up to the cursor
+  fn twins(&self) -> Vec<Track> {
+    self.twins_along(self.path.as_ref().map_or(&[], |path| &path[..])).unwrap_or_default()
+  }
+
+  /// the second track next to the legs so far and `route`, if it can be laid
+  fn twins_along(&self, route: &[Track]) -> Option<Vec<Track>> {
+    if !self.double {
+      return Some(Vec::new());
+    }
+
+    self.twins_of(self.pieces.iter().chain(route.iter()))
+  }
+
+  /// the second track next to `pieces`, if it gets round every bend
+  fn twins_of<'a, I: Iterator<Item = &'a Track>>(&self, pieces: I) -> Option<Vec<Track>> {
+    let step = self.grid.cell_size as i32 / 2;
+    let mut twins: Vec<Track> = Vec::new();
+
+    'pieces: for piece in pieces {
+      let twin = piece.offset(self.grid.cell_size as i32);
+
+      // a turn moved over comes off the diagonal a step further along on the inside of the bend
+      // and a step short on the outside, so the diagonals next to it lose or gain that step
+      while let Some(end) = twins.last().map(|last| last.end()) {
+        let start = twin.start();
+        let Pos(dx, dy) = start.dir.to_pos();
+        let Pos(gx, gy) = start.pos - end.pos;
+
+        if start == end {
+          break;
+        } else if start.dir != end.dir || dx == 0 || dy == 0 || gx * dy != gy * dx {
+          return None;
+        } else if gx * dx > 0 {
+          let next = Connection::new(end.pos + Pos(dx * step, dy * step), end.dir);
+          twins.push(Track::Diag(Diagonal::new(end, next)));
+        } else if let Some(&Track::Diag(_)) = twins.last() {
+          twins.pop();
+        } else if let Track::Diag(_) = twin {
+          continue 'pieces;
+        } else {
+          // two turns with no diagonal between them to take the step out of
+          return None;
+        }
+      }
+
+      twins.push(twin);
+    }
+
+    Some(twins)
+  }
+
+  /// whether the second track can go next to `piece`
+  fn twin_fits(&self, piece: &Track) -> bool {
+    self.can_lay(&piece.offset(self.grid.cell_size as i32))
+  }
+
+  /// whether `twin` is on the map, on ground it can be built on and off every cell in the way
+  fn can_lay(&self, twin: &Track) -> bool {
+    let (w, h) = self.grid.world_size();
+
+    let inside = [twin.start().pos, twin.end().pos].iter().all(|&Pos(x, y)| x > 0 && x < w && y > 0 && y < h);
+    let clear = twin.cells(self.grid.cell_size).iter().all(|cell| !self.taken.contains(cell));
+
+    inside && clear && twin.buildable(&self.terrain) && !self.crosses_blocked(&twin.start(), &twin.end())
+  }
+
+  /// whether the whole second track next to `route` can be laid, at least half a cell from the line itself
+  fn twins_fit(&self, route: &[Track]) -> bool {
+    let near = self.grid.cell_size as i32 / 2;
+    let main = self.pieces.iter()
+        .chain(route.iter())
+        .flat_map(|piece| points(piece, near as f32 / 2.))
+        .collect::<Vec<Pos>>();
+
+    self.twins_along(route).map_or(false, |twins| twins.iter().all(|twin| {
+      self.can_lay(twin) && points(twin, near as f32 / 2.).iter().all(|&Pos(x, y)| {
+        main.iter().all(|&Pos(mx, my)| (x - mx) * (x - mx) + (y - my) * (y - my) >= near * near)
+      })
+    }))
+  }
+
+  /// the pieces in building order, the second track of a double path runs on its own after the first
+  pub fn into_pieces(self) -> Vec<Track> {
+    let twins = self.twins();
+
+    let mut pieces = self.pieces;
+    pieces.extend(self.path.unwrap_or_default());
+    pieces.extend(twins);
+    pieces
   }
 
-  pub fn draw(&self, ctx: &mut Context) -> GameResult<()> {
+  /// paths that can't be paid for are drawn in red
+  pub fn draw(&self, ctx: &mut Context, affordable: bool) -> GameResult<()> {
+    let (fixed, preview) = if affordable {
+      ([0.0, 0.5, 0.2, 1.0], [0.0, 0.7, 0.2, 1.0])
+    } else {
+      ([0.6, 0.1, 0.1, 1.0], [0.9, 0.2, 0.2, 1.0])
+    };
+
+    // draw the fixed legs
+    graphics::set_color(ctx, fixed.into())?;
+
+    for track in self.pieces.iter() {
+      track.draw(ctx)?;
+    }
+
     // draw path
-    graphics::set_color(ctx, [0.0, 0.7, 0.2, 1.0].into())?;
+    graphics::set_color(ctx, preview.into())?;
 
     if let Some(ref path) = self.path {
       for track in path.iter() {
@@ -251,6 +478,28 @@ impl Path {
       }
     }
 
+    for track in self.twins().iter() {
+      track.draw(ctx)?;
+    }
+
+    // waypoints
+    graphics::set_color(ctx, fixed.into())?;
+
+    let mut end = 0;
+    for len in self.legs.iter() {
+      end += len;
+      if end > 0 {
+        graphics::circle(ctx, DrawMode::Fill, self.pieces[end - 1].end().pos.into(), 4., 0.2)?;
+      }
+    }
+
+    // there's no way to get to the cursor from here
+    if let (None, Some(target)) = (&self.path, self.target) {
+      graphics::set_color(ctx, [0.9, 0.1, 0.1, 0.8].into())?;
+      graphics::line(ctx, &[self.head_pos().into(), target.into()], 2.)?;
+      graphics::circle(ctx, DrawMode::Line(2.), target.into(), 10., 0.2)?;
+    }
+
     // current pos
     graphics::set_color(ctx, [1.0, 0.0, 0.0, 1.0].into())?;
     let pos = self.start.pos;
@@ -259,22 +508,107 @@ impl Path {
     Ok(())
   }
 
-  fn estimate(from: &Connection, to: &Pos) -> i32 {
-    ((from.pos.0 - to.0).abs() + (from.pos.1 - to.0).abs()) * 10
+  /// whether a piece from `from` to `to` would run through a blocked cell
+  fn crosses_blocked(&self, from: &Connection, to: &Connection) -> bool {
+    if self.blocked.is_empty() {
+      return false;
+    }
+
+    let cell = self.grid.cell_size as f32;
+    let (fx, fy) = from.pos.to_float();
+    let (tx, ty) = to.pos.to_float();
+
+    // the ends sit on cell edges, so only look in between them
+    [0.25, 0.5, 0.75].iter().any(|perc| {
+      let (x, y) = (fx + (tx - fx) * perc, fy + (ty - fy) * perc);
+      self.blocked.contains(&((x / cell).floor() as i32, (y / cell).floor() as i32))
+    })
   }
 
-  pub fn add_path(&mut self, to: Pos) {
-    let path = self.find_path(to);
+  /// the bridge or tunnel going straight on from `from`, if water or hills are right
+  /// ahead and there's land within reach on the other side
+  fn span(&self, from: &Connection) -> Option<(Connection, Track)> {
+    let gs = self.grid.cell_size as f32;
+    let Pos(dx, dy) = from.dir.to_pos();
+    let (x, y) = from.pos.to_float();
+
+    // diagonals go half a cell each way per step, like diagonal pieces
+    let (step, step_len) = if dx != 0 && dy != 0 { (0.5 * gs, 0.5 * gs * 2f32.sqrt()) } else { (gs, gs) };
+    let point = |steps: f32| Pos((x + dx as f32 * step * steps) as i32, (y + dy as f32 * step * steps) as i32);
+
+    let ground = self.terrain.at(point(0.5));
+    let max = match ground {
+      Ground::Water => MAX_BRIDGE,
+      Ground::Hills => MAX_TUNNEL,
+      Ground::Grass => return None,
+    };
+
+    let mut steps = 1.;
+    while self.terrain.at(point(steps + 0.5)) == ground {
+      steps += 1.;
 
-    self.path = match path {
-      Some(path) => {
-        Some(path.windows(2).map(|c| Track::from((c[0], c[1]))).collect::<Vec<Track>>())
+      if steps * step_len > max * gs {
+        return None;
       }
-      None => None,
+    }
+
+    let end = Connection::new(point(steps), from.dir);
+    let (w, h) = self.grid.world_size();
+    let Pos(ex, ey) = end.pos;
+
+    if ex <= 0 || ex >= w || ey <= 0 || ey >= h {
+      return None;
+    }
+
+    let piece = match ground {
+      Ground::Water => Track::Bridge(Bridge::new(*from, end)),
+      _ => Track::Tunnel(Tunnel::new(*from, end)),
     };
+
+    Some((end, piece))
   }
 
-  pub fn find_path(&self, to: Pos) -> Option<Vec<Connection>> {
+  /// every piece that can be built on from `from`
+  fn next_pieces(&self, from: &Connection) -> Vec<(Connection, Track)> {
+    let mut pieces = from.gen_connections(&self.grid).into_iter()
+        .filter(|(conn, _)| !self.crosses_blocked(from, conn))
+        .map(|(conn, _)| (conn, Track::new(*from, conn, &self.grid)))
+        .filter(|(_, piece)| piece.buildable(&self.terrain))
+        .collect::<Vec<(Connection, Track)>>();
+
+    pieces.extend(self.span(from).filter(|(_, piece)| piece.buildable(&self.terrain)));
+
+    if self.double {
+      pieces.retain(|(_, piece)| self.twin_fits(piece));
+    }
+
+    pieces
+  }
+
+  /// the last few pieces on the way to node `i`, going back into the legs before it
+  fn behind(&self, pieces: &[Option<Track>], children: &[usize], mut i: usize) -> Vec<Track> {
+    let mut behind = Vec::new();
+
+    while i != 0 && behind.len() < BEHIND {
+      behind.extend(pieces[i].clone());
+      i = children[i];
+    }
+
+    behind.extend(self.pieces.iter().rev().take(BEHIND - behind.len()).cloned());
+    behind.reverse();
+    behind
+  }
+
+  fn estimate(from: &Connection, to: &Pos) -> i32 {
+    ((from.pos.0 - to.0).abs() + (from.pos.1 - to.0).abs()) * 10
+  }
+
+  pub fn add_path(&mut self, to: Pos) {
+    self.path = self.find_path(to).filter(|route| self.twins_fit(route));
+    self.target = Some(to);
+  }
+
+  pub fn find_path(&self, to: Pos) -> Option<Vec<Track>> {
     let mut open: Vec<usize> = Vec::new();
     let mut closed: Vec<usize> = Vec::new();
 
@@ -283,8 +617,10 @@ impl Path {
     let mut lookup: HashMap<Connection, usize> = HashMap::new();
 
     let mut children: Vec<usize> = Vec::new();
+    // the piece each node was reached by
+    let mut pieces: Vec<Option<Track>> = Vec::new();
 
-    let head = self.start;
+    let head = self.head();
 
     let start = Node {
       conn: head,
@@ -298,6 +634,7 @@ impl Path {
     open.push(count);
     nodes.push(start);
     children.push(0);
+    pieces.push(None);
     count += 1;
 
     while open.len() > 0 {
@@ -318,14 +655,12 @@ impl Path {
           target = children[target];
         }
 
-        total.push(target);
-
         total.reverse();
 
         return Some(
           total
               .iter()
-              .map(|i| nodes.get(*i).expect("all nodes in the children list should exist").conn)
+              .map(|i| pieces[*i].take().expect("all nodes but the start were reached by a piece"))
               .collect()
         );
       }
@@ -333,8 +668,16 @@ impl Path {
       open.remove_item(&target);
       closed.push(target);
 
-      for (conn, len) in node.conn.gen_connections() {
-        let total_g = node.g_score + len * 10;
+      // the second track has to get round the bends just before as well
+      let behind = if self.double { self.behind(&pieces, &children, target) } else { Vec::new() };
+
+      for (conn, piece) in self.next_pieces(&node.conn) {
+        if self.double && self.twins_of(behind.iter().chain(Some(&piece))).is_none() {
+          continue;
+        }
+
+        // rougher ground, bridges and tunnels count as longer so cheaper routes win
+        let total_g = node.g_score + (piece.len() * piece.multiplier(&self.terrain)) as i32 * 10;
 
         if let Some(i) = lookup.get(&conn) {
           if !closed.contains(i) {
@@ -346,6 +689,7 @@ impl Path {
 
             let mut child = children.get_mut(*i).expect("a children entry should exist for all nodes");
             *child = target;
+            pieces[*i] = Some(piece);
 
             n_node.g_score = total_g;
             n_node.f_score = total_g + Path::estimate(&n_node.conn, &to)
@@ -363,6 +707,7 @@ impl Path {
         open.push(count);
         nodes.push(n_node);
         children.push(target);
+        pieces.push(Some(piece));
         count += 1;
       }
     }
@@ -371,6 +716,13 @@ impl Path {
   }
 }
 
+/// points no more than `every` pixels apart along `piece`
+fn points(piece: &Track, every: f32) -> Vec<Pos> {
+  let steps = (piece.len() / every).ceil().max(1.) as i32;
+
+  (0..steps + 1).map(|i| piece.lerp(i as f32 / steps as f32)).collect()
+}
+
 #[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
 struct Node {
   conn: Connection,
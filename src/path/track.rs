@@ -1,22 +1,47 @@
 use path::{Connection, Pos, Dir};
-use super::GRID_CELL_SIZE;
+use config::Grid;
+use terrain::{Ground, Terrain};
 
 use std::f32::consts::PI;
 
 use ggez::{
-  graphics::{self},
+  graphics::{self, Point2, Vector2},
   GameResult,
   Context,
 };
 
-pub const STRT_LEN: f32 = GRID_CELL_SIZE as f32;
+// how many points along a piece get checked when looking for the closest one
+const CLOSEST_SAMPLES: i32 = 16;
+// how many points along a piece get checked for the ground it's built on
+const COST_SAMPLES: i32 = 4;
+// price of a cell's length of track on flat ground
+const COST_PER_CELL: f32 = 10.;
+// price of stringing a cell's length of track with wires, wherever it is
+const WIRING_PER_CELL: f32 = 4.;
+// how much more than flat ground bridges and tunnels cost, whatever they cross,
+// tunnels undercut laying track over the hills
+const BRIDGE_MULTIPLIER: f32 = 4.;
+const TUNNEL_MULTIPLIER: f32 = 2.;
+// how hard trains can be pushed sideways in a turn, in pixels per second squared
+const LATERAL: f32 = 250.;
+// the steepest a piece can climb, height over length
+const MAX_GRADIENT: f32 = 0.05;
+// half the width of a bridge deck and a tunnel portal, in pixels
+const DECK_WIDTH: f32 = 5.;
+const PORTAL_WIDTH: f32 = 8.;
+// how far apart the masts holding up the wires are and how far they stick out, in pixels
+const MAST_SPACING: f32 = 16.;
+const MAST_WIDTH: f32 = 6.;
 
 pub trait TrackPiece {
   fn start(&self) -> Connection;
   fn end(&self) -> Connection;
 
   fn len(&self) -> f32 {
-    STRT_LEN
+    let (sx, sy) = self.start().pos.to_float();
+    let (ex, ey) = self.end().pos.to_float();
+
+    ((ex - sx).powi(2) + (ey - sy).powi(2)).sqrt()
   }
 
   fn lerp(&self, perc: f32) -> Pos {
@@ -28,6 +53,21 @@ pub trait TrackPiece {
     start + diff
   }
 
+  /// the point `dist` along the piece
+  fn at(&self, dist: f32) -> Pos {
+    self.lerp(dist / self.len())
+  }
+
+  /// returns the percentage along the piece closest to `pos` and the distance to it
+  fn closest(&self, pos: (f32, f32)) -> (f32, f32) {
+    (0..=CLOSEST_SAMPLES).map(|i| {
+      let perc = i as f32 / CLOSEST_SAMPLES as f32;
+      let (x, y) = self.lerp(perc).to_float();
+
+      (perc, ((x - pos.0).powi(2) + (y - pos.1).powi(2)).sqrt())
+    }).fold((0., ::std::f32::MAX), |acc, e| if e.1 < acc.1 { e } else { acc })
+  }
+
   fn draw(&self, ctx: &mut Context) -> GameResult<()> {
     graphics::line(ctx, &[self.start().pos.into(), self.end().pos.into()], 2.)
   }
@@ -63,9 +103,6 @@ pub struct Diagonal {
   end: Connection,
 }
 
-use std::f32::consts::SQRT_2;
-pub const DIAG_LEN: f32 = SQRT_2 * 0.5 * GRID_CELL_SIZE as f32;
-
 impl Diagonal {
   pub fn new(start: Connection, end: Connection) -> Self {
     Diagonal {
@@ -82,19 +119,91 @@ impl TrackPiece for Diagonal {
   fn end(&self) -> Connection {
     self.end
   }
+}
 
-  fn len(&self) -> f32 {
-    DIAG_LEN
+/// a straight span across water
+#[derive(Debug, Clone, PartialOrd, PartialEq)]
+pub struct Bridge {
+  start: Connection,
+  end: Connection,
+}
+
+impl Bridge {
+  pub fn new(start: Connection, end: Connection) -> Self {
+    Bridge {
+      start,
+      end,
+    }
   }
 }
 
-const TURN_RADIUS: f32 = 2.5 * GRID_CELL_SIZE as f32;
-const TURN_ANGLE: f32 = 0.643501102924346923828125;
-// 0.75_f32.atan();
-pub const TURN_LEN: f32 = (TURN_ANGLE * TURN_RADIUS);
+impl TrackPiece for Bridge {
+  fn start(&self) -> Connection {
+    self.start
+  }
+  fn end(&self) -> Connection {
+    self.end
+  }
 
-const TURN_DIVISIONS: i32 = 8;
-const TURN_ANGLE_FRACT: f32 = TURN_ANGLE / TURN_DIVISIONS as f32;
+  /// the rails with the edges of the deck on either side
+  fn draw(&self, ctx: &mut Context) -> GameResult<()> {
+    let (start, end) = (self.start.pos.into(), self.end.pos.into());
+    let side = across(self) * DECK_WIDTH;
+
+    graphics::line(ctx, &[start, end], 2.)?;
+    graphics::line(ctx, &[start + side, end + side], 1.)?;
+    graphics::line(ctx, &[start - side, end - side], 1.)
+  }
+}
+
+/// a straight span under hills
+#[derive(Debug, Clone, PartialOrd, PartialEq)]
+pub struct Tunnel {
+  start: Connection,
+  end: Connection,
+}
+
+impl Tunnel {
+  pub fn new(start: Connection, end: Connection) -> Self {
+    Tunnel {
+      start,
+      end,
+    }
+  }
+}
+
+impl TrackPiece for Tunnel {
+  fn start(&self) -> Connection {
+    self.start
+  }
+  fn end(&self) -> Connection {
+    self.end
+  }
+
+  /// only the portals, what's inside can't be seen
+  fn draw(&self, ctx: &mut Context) -> GameResult<()> {
+    let side = across(self) * PORTAL_WIDTH;
+
+    for pos in [self.start.pos, self.end.pos].iter() {
+      let pos: Point2 = (*pos).into();
+      graphics::line(ctx, &[pos + side, pos - side], 4.)?;
+    }
+
+    Ok(())
+  }
+}
+
+/// a unit vector square to the piece's chord
+fn across<T: TrackPiece>(piece: &T) -> Vector2 {
+  let (sx, sy) = piece.start().pos.to_float();
+  let (ex, ey) = piece.end().pos.to_float();
+  let len = piece.len();
+
+  Vector2::new((sy - ey) / len, (ex - sx) / len)
+}
+
+pub const TURN_ANGLE: f32 = 0.643501102924346923828125;
+// 0.75_f32.atan();
 
 #[derive(Debug, Clone, PartialOrd, PartialEq)]
 pub struct Turn {
@@ -103,15 +212,18 @@ pub struct Turn {
   center: Pos,
   base_ang: f32,
   dir: i8,
+  radius: f32,
+  divisions: i32,
 }
 
 impl Turn {
-  pub fn new(start: Connection, end: Connection) -> Self {
+  /// `radius` is in pixels, `divisions` is how many lines the turn is drawn with
+  pub fn new(start: Connection, end: Connection, radius: f32, divisions: i32) -> Self {
     let prev_pos = start.pos;
     let curr_pos = end.pos;
 
     let matc = |pos: Pos, center: Pos, turn: i8, ang: f32, reverse: bool| -> (Pos, i8, f32) {
-      (Pos((pos.0 as f32 + center.0 as f32 * 2.5) as i32, (pos.1 as f32 + center.1 as f32 * 2.5) as i32), turn, ang * 2. * PI - if reverse { turn as f32 * TURN_ANGLE } else { 0. })
+      (Pos((pos.0 as f32 + center.0 as f32 * radius) as i32, (pos.1 as f32 + center.1 as f32 * radius) as i32), turn, ang * 2. * PI - if reverse { turn as f32 * TURN_ANGLE } else { 0. })
     };
 
     use self::Dir::*;
@@ -145,8 +257,17 @@ impl Turn {
       dir,
       base_ang,
       center,
+      radius,
+      divisions,
     }
   }
+
+  /// the same turn moved over by `by`
+  fn moved(&self, by: Pos) -> Turn {
+    let (start, end) = (Connection::new(self.start.pos + by, self.start.dir), Connection::new(self.end.pos + by, self.end.dir));
+
+    Turn::new(start, end, self.radius, self.divisions)
+  }
 }
 
 impl TrackPiece for Turn {
@@ -157,13 +278,13 @@ impl TrackPiece for Turn {
     self.end
   }
   fn len(&self) -> f32 {
-    TURN_LEN
+    TURN_ANGLE * self.radius
   }
   fn lerp(&self, perc: f32) -> Pos {
     let Pos(cx, cy) = self.center;
     let div = TURN_ANGLE * perc;
 
-    Pos(cx + (TURN_RADIUS * (self.base_ang + div * self.dir as f32).cos()) as i32, cy + (TURN_RADIUS * (self.base_ang + div * self.dir as f32).sin()) as i32)
+    Pos(cx + (self.radius * (self.base_ang + div * self.dir as f32).cos()) as i32, cy + (self.radius * (self.base_ang + div * self.dir as f32).sin()) as i32)
   }
 
   fn draw(&self, ctx: &mut Context) -> GameResult<()> {
@@ -172,10 +293,11 @@ impl TrackPiece for Turn {
     let mut points: Vec<Pos> = Vec::new();
     points.push(self.start.pos);
 
-    let divs = (1..TURN_DIVISIONS).map(|e| e as f32 * TURN_ANGLE_FRACT).collect::<Vec<f32>>();
+    let fract = TURN_ANGLE / self.divisions as f32;
+    let divs = (1..self.divisions).map(|e| e as f32 * fract).collect::<Vec<f32>>();
 
     for div in divs {
-      points.push(Pos(cx + (TURN_RADIUS * (self.base_ang + div * self.dir as f32).cos()) as i32, cy + (TURN_RADIUS * (self.base_ang + div * self.dir as f32).sin()) as i32));
+      points.push(Pos(cx + (self.radius * (self.base_ang + div * self.dir as f32).cos()) as i32, cy + (self.radius * (self.base_ang + div * self.dir as f32).sin()) as i32));
     }
 
     points.push(self.end.pos);
@@ -195,37 +317,152 @@ pub enum Track {
   Diag(Diagonal),
   Turn(Turn),
   Strt(Straight),
+  Bridge(Bridge),
+  Tunnel(Tunnel),
 }
 
-impl From<(Connection, Connection)> for Track {
-  fn from((start, end): (Connection, Connection)) -> Self {
+impl Track {
+  pub fn name(&self) -> &'static str {
+    match self {
+      Track::Turn(_) => "turn",
+      Track::Diag(_) => "diagonal",
+      Track::Strt(_) => "straight",
+      Track::Bridge(_) => "bridge",
+      Track::Tunnel(_) => "tunnel",
+    }
+  }
+
+  /// how fast a train can take the piece without coming off, only turns have a limit
+  pub fn max_speed(&self) -> Option<f32> {
+    match self {
+      Track::Turn(t) => Some((LATERAL * t.radius).sqrt()),
+      _ => None,
+    }
+  }
+
+  /// the ground under the points the piece gets priced by
+  fn ground<'a>(&'a self, terrain: &'a Terrain) -> impl Iterator<Item=Ground> + 'a {
+    (0..COST_SAMPLES).map(move |i| terrain.at(self.lerp((i as f32 + 0.5) / COST_SAMPLES as f32)))
+  }
+
+  /// how many times dearer than flat ground each length of the piece is
+  pub fn multiplier(&self, terrain: &Terrain) -> f32 {
+    match self {
+      Track::Bridge(_) => BRIDGE_MULTIPLIER,
+      Track::Tunnel(_) => TUNNEL_MULTIPLIER,
+      _ => self.ground(terrain).map(|ground| ground.multiplier()).sum::<f32>() / COST_SAMPLES as f32,
+    }
+  }
+
+  /// how much the piece climbs for every pixel of it from start to end, downhill is negative
+  pub fn gradient(&self, terrain: &Terrain) -> f32 {
+    (terrain.elevation(self.end().pos) - terrain.elevation(self.start().pos)) / self.len()
+  }
+
+  /// only bridges can cross water and nothing is built too steep for trains to climb
+  pub fn buildable(&self, terrain: &Terrain) -> bool {
+    let ground = match self {
+      Track::Bridge(_) | Track::Tunnel(_) => true,
+      _ => self.ground(terrain).all(|ground| ground != Ground::Water),
+    };
+
+    ground && self.gradient(terrain).abs() <= MAX_GRADIENT
+  }
+
+  /// what it takes to build the piece, longer pieces and rougher ground cost more
+  pub fn cost(&self, grid: &Grid, terrain: &Terrain) -> i64 {
+    (self.len() / grid.cell_size as f32 * COST_PER_CELL * self.multiplier(terrain)).round() as i64
+  }
+
+  /// what it takes to electrify the piece
+  pub fn wiring_cost(&self, grid: &Grid) -> i64 {
+    (self.len() / grid.cell_size as f32 * WIRING_PER_CELL).round() as i64
+  }
+
+  /// the masts along an electrified piece, square to the track wherever they stand
+  pub fn draw_wires(&self, ctx: &mut Context) -> GameResult<()> {
+    let len = self.len();
+    let masts = (len / MAST_SPACING).ceil().max(1.) as i32;
+
+    for i in 0..masts {
+      let dist = (i as f32 + 0.5) * len / masts as f32;
+      let (bx, by) = self.at((dist - 2.).max(0.)).to_float();
+      let (ax, ay) = self.at((dist + 2.).min(len)).to_float();
+      let dir = ((ax - bx).powi(2) + (ay - by).powi(2)).sqrt();
+
+      if dir == 0. {
+        continue;
+      }
+
+      let pos: Point2 = self.at(dist).into();
+      let side = Vector2::new((by - ay) / dir, (ax - bx) / dir) * MAST_WIDTH;
+      graphics::line(ctx, &[pos - side, pos + side], 1.)?;
+    }
+
+    Ok(())
+  }
+
+  /// the same piece on the next line over to the side, turns are moved over whole to keep
+  /// their shape, so they come off the diagonal a step off from the one next to them
+  pub fn offset(&self, cell: i32) -> Track {
+    let (start, end) = (self.start().shifted(cell), self.end().shifted(cell));
+
+    match self {
+      Track::Turn(t) => {
+        // as far as the end running along the grid goes over
+        let Pos(dx, dy) = t.start.dir.to_pos();
+        let by = if dx == 0 || dy == 0 { start.pos - t.start.pos } else { end.pos - t.end.pos };
+
+        Track::Turn(t.moved(by))
+      }
+      Track::Diag(_) => Track::Diag(Diagonal::new(start, end)),
+      Track::Strt(_) => Track::Strt(Straight::new(start, end)),
+      Track::Bridge(_) => Track::Bridge(Bridge::new(start, end)),
+      Track::Tunnel(_) => Track::Tunnel(Tunnel::new(start, end)),
+    }
+  }
+
+  /// the cells the piece runs through
+  pub fn cells(&self, cell_size: i16) -> Vec<(i32, i32)> {
+    let cell = cell_size as f32;
+
+    (0..4).map(|i| {
+      let (x, y) = self.lerp((i as f32 + 0.5) / 4.).to_float();
+      ((x / cell).floor() as i32, (y / cell).floor() as i32)
+    }).collect()
+  }
+
+  pub fn new(start: Connection, end: Connection, grid: &Grid) -> Self {
     use self::Dir::*;
 
+    let radius = grid.turn_radius * grid.cell_size as f32;
+    let turn = |start, end| Track::Turn(Turn::new(start, end, radius, grid.turn_divisions));
+
     match (start.dir, end.dir) {
       (Up, Up) => Track::Strt(Straight::new(start, end)),
-      (Up, UpLeft) => Track::Turn(Turn::new(start, end)),
-      (Up, UpRight) => Track::Turn(Turn::new(start, end)),
+      (Up, UpLeft) => turn(start, end),
+      (Up, UpRight) => turn(start, end),
       (UpRight, UpRight) => Track::Diag(Diagonal::new(start, end)),
-      (UpRight, Up) => Track::Turn(Turn::new(start, end)),
-      (UpRight, Right) => Track::Turn(Turn::new(start, end)),
+      (UpRight, Up) => turn(start, end),
+      (UpRight, Right) => turn(start, end),
       (Right, Right) => Track::Strt(Straight::new(start, end)),
-      (Right, UpRight) => Track::Turn(Turn::new(start, end)),
-      (Right, DownRight) => Track::Turn(Turn::new(start, end)),
+      (Right, UpRight) => turn(start, end),
+      (Right, DownRight) => turn(start, end),
       (DownRight, DownRight) => Track::Diag(Diagonal::new(start, end)),
-      (DownRight, Right) => Track::Turn(Turn::new(start, end)),
-      (DownRight, Down) => Track::Turn(Turn::new(start, end)),
+      (DownRight, Right) => turn(start, end),
+      (DownRight, Down) => turn(start, end),
       (Down, Down) => Track::Strt(Straight::new(start, end)),
-      (Down, DownRight) => Track::Turn(Turn::new(start, end)),
-      (Down, DownLeft) => Track::Turn(Turn::new(start, end)),
+      (Down, DownRight) => turn(start, end),
+      (Down, DownLeft) => turn(start, end),
       (DownLeft, DownLeft) => Track::Diag(Diagonal::new(start, end)),
-      (DownLeft, Down) => Track::Turn(Turn::new(start, end)),
-      (DownLeft, Left) => Track::Turn(Turn::new(start, end)),
+      (DownLeft, Down) => turn(start, end),
+      (DownLeft, Left) => turn(start, end),
       (Left, Left) => Track::Strt(Straight::new(start, end)),
-      (Left, DownLeft) => Track::Turn(Turn::new(start, end)),
-      (Left, UpLeft) => Track::Turn(Turn::new(start, end)),
+      (Left, DownLeft) => turn(start, end),
+      (Left, UpLeft) => turn(start, end),
       (UpLeft, UpLeft) => Track::Diag(Diagonal::new(start, end)),
-      (UpLeft, Left) => Track::Turn(Turn::new(start, end)),
-      (UpLeft, Up) => Track::Turn(Turn::new(start, end)),
+      (UpLeft, Left) => turn(start, end),
+      (UpLeft, Up) => turn(start, end),
 
       _ => unreachable!("This is not a valid Track"),
     }
@@ -238,6 +475,8 @@ impl TrackPiece for Track {
       Track::Turn(t) => t.start(),
       Track::Diag(t) => t.start(),
       Track::Strt(t) => t.start(),
+      Track::Bridge(t) => t.start(),
+      Track::Tunnel(t) => t.start(),
     }
   }
 
@@ -246,6 +485,8 @@ impl TrackPiece for Track {
       Track::Turn(t) => t.end(),
       Track::Diag(t) => t.end(),
       Track::Strt(t) => t.end(),
+      Track::Bridge(t) => t.end(),
+      Track::Tunnel(t) => t.end(),
     }
   }
 
@@ -254,6 +495,8 @@ impl TrackPiece for Track {
       Track::Turn(t) => t.len(),
       Track::Diag(t) => t.len(),
       Track::Strt(t) => t.len(),
+      Track::Bridge(t) => t.len(),
+      Track::Tunnel(t) => t.len(),
     }
   }
 
@@ -262,6 +505,8 @@ impl TrackPiece for Track {
       Track::Turn(t) => t.lerp(perc),
       Track::Diag(t) => t.lerp(perc),
       Track::Strt(t) => t.lerp(perc),
+      Track::Bridge(t) => t.lerp(perc),
+      Track::Tunnel(t) => t.lerp(perc),
     }
   }
 
@@ -270,6 +515,8 @@ impl TrackPiece for Track {
       Track::Turn(t) => t.draw(ctx),
       Track::Diag(t) => t.draw(ctx),
       Track::Strt(t) => t.draw(ctx),
+      Track::Bridge(t) => t.draw(ctx),
+      Track::Tunnel(t) => t.draw(ctx),
     }
   }
 }
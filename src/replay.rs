@@ -0,0 +1,397 @@
+use std::{
+  fs::File,
+  io::{Read, Write},
+};
+
+use ggez::{
+  GameResult,
+  GameError,
+};
+
+use config::{Config, Grid};
+use industry::Kind;
+use path::{Dir, Pos};
+use train::{
+  spawn::SpawnConfig,
+  traction::Traction,
+};
+use stats::Stats;
+use world::{Command, World};
+
+/// every command that went into a run together with the tick it happened on
+///
+/// saved as plain text, the seed, how long the run went on for and the settings the world was
+/// made with first and then one command per line:
+///
+/// ```text
+/// seed 42
+/// ticks 3600
+/// grid 40 25 32 2.5 8
+/// budget 5000
+/// blank off
+/// derailments on
+/// 120 start_path 64 96 right
+/// 300 commit_path 320 96
+/// ```
+pub struct Replay {
+  pub seed: u32,
+  // how long the run went on for
+  pub ticks: u64,
+  // the settings that change how the world turns out
+  grid: Grid,
+  budget: Option<i64>,
+  blank: bool,
+  derailments: bool,
+  commands: Vec<(u64, Command)>,
+  // how many commands have been played back already
+  played: usize,
+}
+
+impl Replay {
+  pub fn new(seed: u32, config: &Config) -> Self {
+    Replay {
+      seed,
+      ticks: 0,
+      grid: config.grid,
+      budget: config.budget,
+      blank: config.blank,
+      derailments: config.derailments,
+      commands: Vec::new(),
+      played: 0,
+    }
+  }
+
+  pub fn record(&mut self, tick: u64, cmd: Command) {
+    self.commands.push((tick, cmd));
+  }
+
+  /// the commands that have to be applied before stepping from `tick`
+  pub fn due(&mut self, tick: u64) -> Vec<Command> {
+    let due = self.commands[self.played..].iter()
+        .take_while(|&&(at, _)| at <= tick)
+        .map(|&(_, cmd)| cmd)
+        .collect::<Vec<Command>>();
+
+    self.played += due.len();
+
+    due
+  }
+
+  pub fn finished(&self) -> bool {
+    self.played >= self.commands.len()
+  }
+
+  /// plays the whole run out on `world` without a window, `stats` are kept on every step
+  pub fn play(&mut self, world: &mut World, stats: &mut Stats) {
+    while world.tick < self.ticks {
+      for cmd in self.due(world.tick) {
+        world.apply(&cmd);
+      }
+
+      world.step();
+      stats.observe(world);
+    }
+  }
+
+  /// `config` with the settings the replay was recorded with, so it plays out the same
+  pub fn configure(&self, config: &Config) -> Config {
+    let mut config = config.clone();
+    config.grid = self.grid;
+    config.budget = self.budget;
+    config.blank = self.blank;
+    config.derailments = self.derailments;
+    config
+  }
+
+  pub fn load(path: &str) -> GameResult<Replay> {
+    let mut text = String::new();
+    File::open(path)?.read_to_string(&mut text)?;
+
+    Replay::from_text(&text)
+  }
+
+  pub fn save(&self, path: &str) -> GameResult<()> {
+    File::create(path)?.write_all(self.to_text().as_bytes())?;
+
+    Ok(())
+  }
+
+  fn from_text(text: &str) -> GameResult<Replay> {
+    let mut lines = text.lines().enumerate().filter(|&(_, line)| !line.trim().is_empty());
+
+    let (at, seed) = header(lines.next(), "seed")?;
+    let seed = match seed[..] {
+      [seed] => parse(seed, at)?,
+      _ => return Err(invalid(at)),
+    };
+
+    let (at, ticks) = header(lines.next(), "ticks")?;
+    let ticks = match ticks[..] {
+      [ticks] => parse(ticks, at)?,
+      _ => return Err(invalid(at)),
+    };
+
+    let (at, grid) = header(lines.next(), "grid")?;
+    let grid = match grid[..] {
+      [w, h, cell_size, turn_radius, turn_divisions] => Grid {
+        size: (parse(w, at)?, parse(h, at)?),
+        cell_size: parse(cell_size, at)?,
+        turn_radius: parse(turn_radius, at)?,
+        turn_divisions: parse(turn_divisions, at)?,
+      },
+      _ => return Err(invalid(at)),
+    };
+    grid.validate()?;
+
+    let (at, budget) = header(lines.next(), "budget")?;
+    let budget = match budget[..] {
+      ["none"] => None,
+      [budget] => Some(parse(budget, at)?),
+      _ => return Err(invalid(at)),
+    };
+
+    let (at, blank) = header(lines.next(), "blank")?;
+    let blank = switch(&blank, at)?;
+    let (at, derailments) = header(lines.next(), "derailments")?;
+    let derailments = switch(&derailments, at)?;
+
+    let mut replay = Replay {
+      seed,
+      ticks,
+      grid,
+      budget,
+      blank,
+      derailments,
+      commands: Vec::new(),
+      played: 0,
+    };
+
+    for (i, line) in lines {
+      let (tick, cmd) = decode(line).ok_or_else(|| invalid(i + 1))?;
+      replay.record(tick, cmd);
+    }
+
+    Ok(replay)
+  }
+
+  fn to_text(&self) -> String {
+    let mut text = format!("seed {}\nticks {}\n", self.seed, self.ticks);
+
+    text += &format!("grid {} {} {} {} {}\n", self.grid.size.0, self.grid.size.1, self.grid.cell_size,
+                     self.grid.turn_radius, self.grid.turn_divisions);
+    text += &match self.budget {
+      Some(budget) => format!("budget {}\n", budget),
+      None => "budget none\n".to_owned(),
+    };
+    text += &format!("blank {}\n", if self.blank { "on" } else { "off" });
+    text += &format!("derailments {}\n", if self.derailments { "on" } else { "off" });
+
+    for &(tick, cmd) in self.commands.iter() {
+      text += &format!("{} {}\n", tick, encode(&cmd));
+    }
+
+    text
+  }
+}
+
+/// what the world ended up like, to tell whether two runs played out the same
+pub fn summary(world: &World) -> String {
+  let mut summary = format!(
+    "tick: {}\nbudget: {}\ntracks: {}\ntrains: {}\n",
+    world.tick,
+    world.budget.map_or("unlimited".to_owned(), |budget| budget.to_string()),
+    world.tracks.len(),
+    world.trains.len()
+  );
+
+  for (i, train) in world.trains.iter().enumerate() {
+    let (x, y) = train.head_pos();
+    summary += &format!("train #{}: at {} {}, speed {}, {} cars\n", i, x, y, train.speed(), train.cars());
+  }
+
+  summary
+}
+
+fn invalid(line: usize) -> GameError {
+  GameError::ResourceLoadError(format!("invalid replay line {}", line))
+}
+
+/// the line number and values of a header line, which has to be there and start with `name`
+fn header<'a>(line: Option<(usize, &'a str)>, name: &str) -> GameResult<(usize, Vec<&'a str>)> {
+  match line {
+    Some((i, line)) => {
+      let mut words = line.split_whitespace();
+
+      if words.next() == Some(name) {
+        Ok((i + 1, words.collect()))
+      } else {
+        Err(invalid(i + 1))
+      }
+    }
+    None => Err(GameError::ResourceLoadError(format!("replay is missing its {} line", name))),
+  }
+}
+
+fn parse<T: ::std::str::FromStr>(value: &str, line: usize) -> GameResult<T> {
+  value.parse().map_err(|_| invalid(line))
+}
+
+fn switch(values: &[&str], line: usize) -> GameResult<bool> {
+  match *values {
+    ["on"] => Ok(true),
+    ["off"] => Ok(false),
+    _ => Err(invalid(line)),
+  }
+}
+
+// floats are written with `{}` so they read back to exactly the same value
+fn encode(cmd: &Command) -> String {
+  match *cmd {
+    Command::StartPath(Pos(x, y), dir, false) => format!("start_path {} {} {}", x, y, dir.name()),
+    Command::StartPath(Pos(x, y), dir, true) => format!("start_path {} {} {} double", x, y, dir.name()),
+    Command::AddWaypoint(Pos(x, y)) => format!("add_waypoint {} {}", x, y),
+    Command::RemoveWaypoint => "remove_waypoint".to_owned(),
+    Command::CancelPath => "cancel_path".to_owned(),
+    Command::CommitPath(Pos(x, y)) => format!("commit_path {} {}", x, y),
+    Command::RemoveTrack(track) => format!("remove_track {}", track),
+    Command::PlaceSignal(track, dist) => format!("place_signal {} {}", track, dist),
+    Command::ToggleSignal(signal) => format!("toggle_signal {}", signal),
+    Command::RemoveSignal(signal) => format!("remove_signal {}", signal),
+    Command::PlaceStation(track, dist) => format!("place_station {} {}", track, dist),
+    Command::RemoveStation(station) => format!("remove_station {}", station),
+    Command::SetLimit(track, Some(limit)) => format!("set_limit {} {}", track, limit),
+    Command::SetLimit(track, None) => format!("set_limit {} none", track),
+    Command::Electrify(track, on) => format!("electrify {} {}", track, if on { "on" } else { "off" }),
+    Command::PlaceIndustry(kind, (x, y)) => format!("place_industry {} {} {}", kind.name(), x, y),
+    Command::RemoveIndustry(industry) => format!("remove_industry {}", industry),
+    Command::PlaceDepot(track, at_end) => format!("place_depot {} {}", track, if at_end { "end" } else { "start" }),
+    Command::RemoveDepot(depot) => format!("remove_depot {}", depot),
+    Command::BuyTrain(depot, config) => format!(
+      "buy_train {} {} {} {} {} {}",
+      depot, config.speed, config.cars, config.spacing, config.car_len, config.traction.name()
+    ),
+    Command::ReleaseTrain(depot, train) => format!("release_train {} {}", depot, train),
+    Command::SpawnTrain { track, dist, speed, config } => format!(
+      "spawn_train {} {} {} {} {} {} {}",
+      track, dist, speed, config.cars, config.spacing, config.car_len, config.traction.name()
+    ),
+    Command::StopTrain(train) => format!("stop_train {}", train),
+    Command::ReverseTrain(train) => format!("reverse_train {}", train),
+    Command::SplitTrain(train, keep) => format!("split_train {} {}", train, keep),
+    Command::AddCar(train) => format!("add_car {}", train),
+    Command::RemoveCar(train) => format!("remove_car {}", train),
+    Command::SendToDepot(train) => format!("send_to_depot {}", train),
+    Command::RemoveTrain(train) => format!("remove_train {}", train),
+  }
+}
+
+fn decode(line: &str) -> Option<(u64, Command)> {
+  let words = line.split_whitespace().collect::<Vec<&str>>();
+  let (tick, words) = words.split_first()?;
+  let (name, args) = words.split_first()?;
+
+  let cmd = match (*name, args) {
+    ("start_path", [x, y, dir]) => Command::StartPath(Pos(x.parse().ok()?, y.parse().ok()?), Dir::from_name(dir)?, false),
+    ("start_path", [x, y, dir, "double"]) => Command::StartPath(Pos(x.parse().ok()?, y.parse().ok()?), Dir::from_name(dir)?, true),
+    ("add_waypoint", [x, y]) => Command::AddWaypoint(Pos(x.parse().ok()?, y.parse().ok()?)),
+    ("remove_waypoint", []) => Command::RemoveWaypoint,
+    ("cancel_path", []) => Command::CancelPath,
+    ("commit_path", [x, y]) => Command::CommitPath(Pos(x.parse().ok()?, y.parse().ok()?)),
+    ("remove_track", [track]) => Command::RemoveTrack(track.parse().ok()?),
+    ("place_signal", [track, dist]) => Command::PlaceSignal(track.parse().ok()?, dist.parse().ok()?),
+    ("toggle_signal", [signal]) => Command::ToggleSignal(signal.parse().ok()?),
+    ("remove_signal", [signal]) => Command::RemoveSignal(signal.parse().ok()?),
+    ("place_station", [track, dist]) => Command::PlaceStation(track.parse().ok()?, dist.parse().ok()?),
+    ("remove_station", [station]) => Command::RemoveStation(station.parse().ok()?),
+    ("set_limit", [track, "none"]) => Command::SetLimit(track.parse().ok()?, None),
+    ("set_limit", [track, limit]) => Command::SetLimit(track.parse().ok()?, Some(limit.parse().ok()?)),
+    ("electrify", [track, "on"]) => Command::Electrify(track.parse().ok()?, true),
+    ("electrify", [track, "off"]) => Command::Electrify(track.parse().ok()?, false),
+    ("place_industry", [kind, x, y]) => Command::PlaceIndustry(Kind::from_name(kind)?, (x.parse().ok()?, y.parse().ok()?)),
+    ("remove_industry", [industry]) => Command::RemoveIndustry(industry.parse().ok()?),
+    ("place_depot", [track, "end"]) => Command::PlaceDepot(track.parse().ok()?, true),
+    ("place_depot", [track, "start"]) => Command::PlaceDepot(track.parse().ok()?, false),
+    ("remove_depot", [depot]) => Command::RemoveDepot(depot.parse().ok()?),
+    ("buy_train", [depot, speed, cars, spacing, car_len, traction]) => Command::BuyTrain(depot.parse().ok()?, SpawnConfig {
+      traction: Traction::from_name(traction)?,
+      cars: cars.parse().ok()?,
+      spacing: spacing.parse().ok()?,
+      car_len: car_len.parse().ok()?,
+      speed: speed.parse().ok()?,
+    }),
+    ("release_train", [depot, train]) => Command::ReleaseTrain(depot.parse().ok()?, train.parse().ok()?),
+    // replays from before there were engines to pick from
+    ("spawn_train", [_, _, _, _, _, _]) => return decode(&format!("{} {}", line, Traction::default().name())),
+    ("spawn_train", [track, dist, speed, cars, spacing, car_len, traction]) => Command::SpawnTrain {
+      track: track.parse().ok()?,
+      dist: dist.parse().ok()?,
+      speed: speed.parse().ok()?,
+      config: SpawnConfig {
+        traction: Traction::from_name(traction)?,
+        cars: cars.parse().ok()?,
+        spacing: spacing.parse().ok()?,
+        car_len: car_len.parse().ok()?,
+        speed: speed.parse::<f32>().ok()?.abs(),
+      },
+    },
+    ("stop_train", [train]) => Command::StopTrain(train.parse().ok()?),
+    ("reverse_train", [train]) => Command::ReverseTrain(train.parse().ok()?),
+    ("split_train", [train, keep]) => Command::SplitTrain(train.parse().ok()?, keep.parse().ok()?),
+    ("add_car", [train]) => Command::AddCar(train.parse().ok()?),
+    ("remove_car", [train]) => Command::RemoveCar(train.parse().ok()?),
+    ("send_to_depot", [train]) => Command::SendToDepot(train.parse().ok()?),
+    ("remove_train", [train]) => Command::RemoveTrain(train.parse().ok()?),
+    _ => return None,
+  };
+
+  Some((tick.parse().ok()?, cmd))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn plays_back_the_same() {
+    let mut config = Config::default();
+    config.blank = true;
+    config.budget = Some(100000);
+
+    let commands = [
+      (0, Command::StartPath(Pos(64, 96), Dir::Right, false)),
+      (0, Command::CommitPath(Pos(960, 96))),
+      (10, Command::SpawnTrain { track: 0, dist: 0., speed: 200., config: SpawnConfig::default() }),
+      (600, Command::ReverseTrain(0)),
+      (1200, Command::StopTrain(0)),
+      (1500, Command::StopTrain(0)),
+    ];
+
+    let mut record = Replay::new(7, &config);
+    let mut world = config.world(7);
+    let mut stats = Stats::new();
+
+    for tick in 0..3000 {
+      for &(_, cmd) in commands.iter().filter(|&&(at, _)| at == tick) {
+        record.record(tick, cmd);
+        world.apply(&cmd);
+      }
+
+      world.step();
+      stats.observe(&world);
+    }
+    record.ticks = world.tick;
+    assert!(!world.trains.is_empty());
+
+    let text = record.to_text();
+    let mut replay = Replay::from_text(&text).unwrap();
+    assert_eq!(replay.to_text(), text);
+
+    let mut played = replay.configure(&Config::default()).world(replay.seed);
+    let mut played_stats = Stats::new();
+    replay.play(&mut played, &mut played_stats);
+
+    assert!(replay.finished());
+    assert_eq!(played_stats.report(), stats.report());
+    assert_eq!(summary(&played), summary(&world));
+  }
+}
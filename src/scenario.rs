@@ -0,0 +1,279 @@
+use std::fs::File;
+
+use ron;
+
+use ggez::{
+  GameResult,
+  GameError,
+};
+
+use config::Grid;
+use path::{Dir, Pos};
+use industry::Kind;
+use mapgen;
+use terrain::Ground;
+use train::spawn::SpawnConfig;
+use world::{
+  Command,
+  World,
+};
+
+/// a layout and the trains running on it, loaded from a ron file:
+///
+/// ```text
+/// (
+///   seed: 1,
+///   generate: false,
+///   grid: Some((size: (60, 40))),
+///   budget: Some(2000),
+///   terrain: [
+///     (from: (10, 0), to: (14, 8), ground: Hills),
+///   ],
+///   heights: [
+///     (from: (11, 0), to: (13, 9), height: 12.0),
+///   ],
+///   paths: [
+///     (start: (64, 112), dir: Right, to: (960, 112)),
+///     (start: (64, 400), dir: Right, to: (960, 400), double: true, electrified: true),
+///   ],
+///   towns: [
+///     (name: "Ashby", cell: (4, 6), population: 400),
+///   ],
+///   industries: [
+///     (kind: Mine, cell: (3, 5)),
+///   ],
+///   stations: [
+///     (track: 2, dist: 0.0),
+///     (track: 26, dist: 0.0),
+///   ],
+///   limits: [
+///     (track: 12, speed: 60.0),
+///   ],
+///   depots: [
+///     (track: 0),
+///     (track: 25, at_end: true),
+///   ],
+///   derailments: true,
+///   trains: [
+///     (track: 0, dist: 0.0),
+///     (track: 20, dist: 0.0, forward: false, config: (cars: 2, speed: 120.0)),
+///     (track: 40, dist: 0.0, config: (traction: Electric, speed: 300.0)),
+///   ],
+/// )
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct Scenario {
+  #[serde(default)]
+  seed: u32,
+  // starts from the map the seed generates, the rest is built on top of it
+  #[serde(default)]
+  generate: bool,
+  // replaces the grid from the settings
+  #[serde(default)]
+  grid: Option<Grid>,
+  // what's left for the player after the layout is built
+  #[serde(default)]
+  budget: Option<i64>,
+  #[serde(default)]
+  terrain: Vec<AreaDef>,
+  #[serde(default)]
+  heights: Vec<HeightDef>,
+  #[serde(default)]
+  paths: Vec<PathDef>,
+  #[serde(default)]
+  towns: Vec<TownDef>,
+  #[serde(default)]
+  industries: Vec<IndustryDef>,
+  #[serde(default)]
+  stations: Vec<SpotDef>,
+  #[serde(default)]
+  limits: Vec<LimitDef>,
+  #[serde(default)]
+  depots: Vec<DepotDef>,
+  // trains come off turns they take too fast
+  #[serde(default)]
+  derailments: bool,
+  #[serde(default)]
+  trains: Vec<TrainDef>,
+}
+
+/// a rectangle of cells, both corners included
+#[derive(Debug, Deserialize)]
+struct AreaDef {
+  from: (i32, i32),
+  to: (i32, i32),
+  ground: Ground,
+}
+
+/// a rectangle of nodes raised to `height`, both corners included
+#[derive(Debug, Deserialize)]
+struct HeightDef {
+  from: (i32, i32),
+  to: (i32, i32),
+  height: f32,
+}
+
+#[derive(Debug, Deserialize)]
+struct PathDef {
+  start: Pos,
+  dir: Dir,
+  to: Pos,
+  #[serde(default)]
+  double: bool,
+  // strings wires over every piece of it
+  #[serde(default)]
+  electrified: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct TownDef {
+  name: String,
+  cell: (i32, i32),
+  population: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct IndustryDef {
+  kind: Kind,
+  // top left cell
+  cell: (i32, i32),
+}
+
+/// somewhere along a track
+#[derive(Debug, Deserialize)]
+struct SpotDef {
+  track: usize,
+  dist: f32,
+}
+
+#[derive(Debug, Deserialize)]
+struct LimitDef {
+  track: usize,
+  speed: f32,
+}
+
+/// a depot at the start of a piece, or at its end
+#[derive(Debug, Deserialize)]
+struct DepotDef {
+  track: usize,
+  #[serde(default)]
+  at_end: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrainDef {
+  track: usize,
+  // where the tail of the train starts
+  dist: f32,
+  #[serde(default = "forward")]
+  forward: bool,
+  #[serde(default)]
+  config: SpawnConfig,
+}
+
+fn forward() -> bool {
+  true
+}
+
+impl Scenario {
+  pub fn load(path: &str) -> GameResult<Scenario> {
+    let file = File::open(path)?;
+
+    ron::de::from_reader(file)
+        .map_err(|e| GameError::ResourceLoadError(format!("invalid scenario {}: {}", path, e)))
+  }
+
+  /// lays out the tracks and places the trains, the same way a player would
+  pub fn build(&self, grid: Grid) -> GameResult<World> {
+    let grid = self.grid.unwrap_or(grid);
+    grid.validate()?;
+
+    let mut world = World::new(self.seed, grid);
+
+    if self.generate {
+      mapgen::generate(&mut world);
+    }
+
+    for area in self.terrain.iter() {
+      for x in area.from.0..area.to.0 + 1 {
+        for y in area.from.1..area.to.1 + 1 {
+          world.terrain.set((x, y), area.ground);
+        }
+      }
+    }
+
+    for area in self.heights.iter() {
+      for x in area.from.0..area.to.0 + 1 {
+        for y in area.from.1..area.to.1 + 1 {
+          world.terrain.set_height((x, y), area.height);
+        }
+      }
+    }
+
+    for (i, path) in self.paths.iter().enumerate() {
+      let first = world.tracks.len();
+
+      world.apply(&Command::StartPath(path.start, path.dir, path.double));
+      world.apply(&Command::CommitPath(path.to));
+
+      // everything after it counts on its pieces being there
+      if world.path.is_some() || world.tracks.len() == first {
+        return Err(GameError::ResourceLoadError(format!(
+          "path {} from {:?} to {:?} can't be built, check that there's a way through",
+          i, path.start, path.to
+        )));
+      }
+
+      if path.electrified {
+        for track in first..world.tracks.len() {
+          world.apply(&Command::Electrify(track, true));
+        }
+      }
+    }
+
+    for industry in self.industries.iter() {
+      world.apply(&Command::PlaceIndustry(industry.kind, industry.cell));
+    }
+
+    // towns go up around the track and the industries
+    for town in self.towns.iter() {
+      world.found_town(town.name.clone(), town.cell, town.population);
+    }
+
+    for station in self.stations.iter() {
+      world.apply(&Command::PlaceStation(station.track, station.dist));
+    }
+
+    for limit in self.limits.iter() {
+      world.apply(&Command::SetLimit(limit.track, Some(limit.speed)));
+    }
+
+    for depot in self.depots.iter() {
+      world.apply(&Command::PlaceDepot(depot.track, depot.at_end));
+    }
+
+    world.derailments = self.derailments;
+
+    for (i, train) in self.trains.iter().enumerate() {
+      let speed = if train.forward { train.config.speed } else { -train.config.speed };
+
+      if !world.can_spawn(train.track, train.dist, &train.config) {
+        return Err(GameError::ResourceLoadError(format!(
+          "train {} doesn't fit on track {} at {}, check the track, its cars and that the line is long and wired enough",
+          i, train.track, train.dist
+        )));
+      }
+
+      world.apply(&Command::SpawnTrain {
+        track: train.track,
+        dist: train.dist,
+        speed,
+        config: train.config,
+      });
+    }
+
+    world.budget = self.budget;
+
+    Ok(world)
+  }
+}
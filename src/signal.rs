@@ -0,0 +1,53 @@
+use ggez::{
+  Context,
+  graphics::{self, DrawMode},
+  GameResult,
+};
+
+use path::{
+  track::{
+    Track,
+    TrackPiece,
+  },
+  Pos,
+};
+
+/// a signal beside the track, trains wait in front of it while it's red
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
+pub struct Signal {
+  pub track: usize,
+  pub dist: f32,
+  pub clear: bool,
+}
+
+impl Signal {
+  pub fn new(track: usize, dist: f32) -> Self {
+    Signal {
+      track,
+      dist,
+      clear: true,
+    }
+  }
+
+  pub fn toggle(&mut self) {
+    self.clear = !self.clear;
+  }
+
+  pub fn pos(&self, tracks: &Vec<Track>) -> Pos {
+    tracks[self.track].at(self.dist)
+  }
+
+  pub fn draw(&self, ctx: &mut Context, tracks: &Vec<Track>) -> GameResult<()> {
+    let pos = self.pos(tracks);
+
+    if self.clear {
+      graphics::set_color(ctx, [0.1, 0.8, 0.1, 1.0].into())?;
+    } else {
+      graphics::set_color(ctx, [0.9, 0.1, 0.1, 1.0].into())?;
+    }
+    graphics::circle(ctx, DrawMode::Fill, pos.into(), 6., 0.2)?;
+
+    graphics::set_color(ctx, [0.0, 0.0, 0.0, 1.0].into())?;
+    graphics::circle(ctx, DrawMode::Line(1.), pos.into(), 6., 0.2)
+  }
+}
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use ggez::{
+  Context,
+  graphics::{self, DrawMode, Rect},
+  GameResult,
+};
+
+use cargo::Cargo;
+use path::{
+  track::{
+    Track,
+    TrackPiece,
+  },
+  Pos,
+};
+
+const SIZE: (f32, f32) = (20., 12.);
+
+// how many cells away a station can be and still serve an industry or a town
+pub const CATCHMENT: f32 = 4.;
+// nobody waits around once this many are waiting already
+pub const MAX_WAITING: u32 = 200;
+
+/// a stop along the line, cargo waits here for a train
+#[derive(Debug, Clone, PartialEq)]
+pub struct Station {
+  pub track: usize,
+  pub dist: f32,
+  waiting: HashMap<Cargo, u32>,
+}
+
+impl Station {
+  pub fn new(track: usize, dist: f32) -> Self {
+    Station {
+      track,
+      dist,
+      waiting: HashMap::new(),
+    }
+  }
+
+  /// cargo dropped off by a nearby industry or town
+  pub fn receive(&mut self, cargo: Cargo, amount: u32) {
+    let waiting = self.waiting.entry(cargo).or_insert(0);
+    *waiting = (*waiting + amount).min(MAX_WAITING);
+  }
+
+  pub fn waiting(&self, cargo: Cargo) -> u32 {
+    self.waiting.get(&cargo).cloned().unwrap_or(0)
+  }
+
+  /// hands over up to `max` of `cargo`
+  pub fn take(&mut self, cargo: Cargo, max: u32) -> u32 {
+    let waiting = self.waiting.entry(cargo).or_insert(0);
+    let amount = (*waiting).min(max);
+    *waiting -= amount;
+
+    amount
+  }
+
+  pub fn pos(&self, tracks: &Vec<Track>) -> Pos {
+    tracks[self.track].at(self.dist)
+  }
+
+  pub fn draw(&self, ctx: &mut Context, tracks: &Vec<Track>) -> GameResult<()> {
+    let (x, y) = self.pos(tracks).to_float();
+    let rect = Rect::new(x - SIZE.0 / 2., y - SIZE.1 / 2., SIZE.0, SIZE.1);
+
+    graphics::set_color(ctx, [0.3, 0.5, 0.9, 0.8].into())?;
+    graphics::rectangle(ctx, DrawMode::Fill, rect)?;
+
+    graphics::set_color(ctx, [0.0, 0.0, 0.0, 1.0].into())?;
+    graphics::rectangle(ctx, DrawMode::Line(1.), rect)
+  }
+}
@@ -0,0 +1,146 @@
+use std::{
+  fs::File,
+  io::Write,
+};
+
+use ggez::GameResult;
+
+use train::Status;
+use world::{
+  Event,
+  World,
+  TICK,
+};
+
+/// numbers collected over a run, for comparing layouts
+pub struct Stats {
+  ticks: u64,
+  collisions: u32,
+  // trains wrecked on the buffers or a turn
+  wrecks: u32,
+  arrivals: Vec<u32>,
+  // how many collisions each train was part of
+  crashes: Vec<u32>,
+  revenue: Vec<i64>,
+  // sum of every tick's speed, per train
+  speed_sum: Vec<f32>,
+  // what trains did before they went into a depot, so the totals still add up
+  stored_arrivals: u32,
+  stored_revenue: i64,
+}
+
+impl Stats {
+  pub fn new() -> Self {
+    Stats {
+      ticks: 0,
+      collisions: 0,
+      wrecks: 0,
+      arrivals: Vec::new(),
+      crashes: Vec::new(),
+      revenue: Vec::new(),
+      speed_sum: Vec::new(),
+      stored_arrivals: 0,
+      stored_revenue: 0,
+    }
+  }
+
+  /// takes note of what happened during the last step
+  pub fn observe(&mut self, world: &World) {
+    self.ticks += 1;
+
+    // new trains need room before their events come in, coupled and stored ones go after,
+    // counting the ones that were taken out during the step before they were ever seen
+    let gone = world.events.iter()
+        .filter(|event| match **event {
+          Event::Coupled(..) | Event::Stored(_) => true,
+          _ => false,
+        })
+        .count();
+    let trains = (world.trains.len() + gone).max(self.arrivals.len());
+    self.arrivals.resize(trains, 0);
+    self.crashes.resize(trains, 0);
+    self.revenue.resize(trains, 0);
+    self.speed_sum.resize(trains, 0.);
+
+    for event in world.events.iter() {
+      match *event {
+        Event::Arrived(train) => self.arrivals[train] += 1,
+        Event::Collision(a, b) => {
+          self.collisions += 1;
+          self.crashes[a] += 1;
+          self.crashes[b] += 1;
+        }
+        Event::Crashed(_) => self.wrecks += 1,
+        Event::Delivered(train, revenue) => self.revenue[train] += revenue,
+        Event::Coupled(train, other) => {
+          // the coupled train's numbers count towards the one it joined
+          self.arrivals[train] += self.arrivals.remove(other);
+          self.crashes[train] += self.crashes.remove(other);
+          self.revenue[train] += self.revenue.remove(other);
+          self.speed_sum[train] += self.speed_sum.remove(other);
+        }
+        Event::Stored(train) => {
+          // it comes back out as a new train, if it does at all
+          self.stored_arrivals += self.arrivals.remove(train);
+          self.stored_revenue += self.revenue.remove(train);
+          self.crashes.remove(train);
+          self.speed_sum.remove(train);
+        }
+      }
+    }
+
+    let trains = world.trains.len();
+    self.arrivals.resize(trains, 0);
+    self.crashes.resize(trains, 0);
+    self.revenue.resize(trains, 0);
+    self.speed_sum.resize(trains, 0.);
+
+    for (i, train) in world.trains.iter().enumerate() {
+      if train.status() == Status::Running {
+        self.speed_sum[i] += train.speed();
+      }
+    }
+  }
+
+  fn average_speed(&self, train: usize) -> f32 {
+    if self.ticks == 0 { 0. } else { self.speed_sum[train] / self.ticks as f32 }
+  }
+
+  pub fn report(&self) -> String {
+    let mut report = format!(
+      "ticks: {} ({:.1}s)\ncollisions: {}\nwrecks: {}\narrivals: {}\nrevenue: {}\n",
+      self.ticks,
+      self.ticks as f32 * TICK,
+      self.collisions,
+      self.wrecks,
+      self.arrivals.iter().sum::<u32>() + self.stored_arrivals,
+      self.revenue.iter().sum::<i64>() + self.stored_revenue
+    );
+
+    for (i, arrivals) in self.arrivals.iter().enumerate() {
+      report += &format!(
+        "train #{}: {} arrivals, {} collisions, revenue {}, average speed {:.1}\n",
+        i,
+        arrivals,
+        self.crashes[i],
+        self.revenue[i],
+        self.average_speed(i)
+      );
+    }
+
+    report
+  }
+
+  /// writes a csv file with one line per train
+  pub fn save(&self, path: &str) -> GameResult<()> {
+    let mut file = File::create(path)?;
+
+    writeln!(file, "train,arrivals,collisions,revenue,average_speed")?;
+
+    for (i, arrivals) in self.arrivals.iter().enumerate() {
+      writeln!(file, "{},{},{},{},{}", i, arrivals, self.crashes[i], self.revenue[i], self.average_speed(i))?;
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,133 @@
+use ggez::{
+  Context,
+  graphics::{self, DrawMode, Rect},
+  GameResult,
+};
+
+use config::Grid;
+use path::Pos;
+
+/// what a single cell of the map is made of
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq, Deserialize)]
+pub enum Ground {
+  Grass,
+  Hills,
+  Water,
+}
+
+impl Ground {
+  /// how much more track costs to build here than on flat grass
+  pub fn multiplier(&self) -> f32 {
+    match self {
+      Ground::Grass => 1.,
+      Ground::Hills => 2.5,
+      Ground::Water => 6.,
+    }
+  }
+}
+
+/// the ground under every cell of the grid and how high up every node between the cells is
+#[derive(Clone)]
+pub struct Terrain {
+  grid: Grid,
+  cells: Vec<Ground>,
+  // in pixels, one more each way than there are cells
+  heights: Vec<f32>,
+}
+
+impl Terrain {
+  /// flat grass everywhere
+  pub fn new(grid: Grid) -> Self {
+    Terrain {
+      grid,
+      cells: vec![Ground::Grass; grid.size.0 as usize * grid.size.1 as usize],
+      heights: vec![0.; (grid.size.0 as usize + 1) * (grid.size.1 as usize + 1)],
+    }
+  }
+
+  fn index(&self, x: i32, y: i32) -> Option<usize> {
+    let (w, h) = (self.grid.size.0 as i32, self.grid.size.1 as i32);
+
+    if x < 0 || y < 0 || x >= w || y >= h {
+      None
+    } else {
+      Some((y * w + x) as usize)
+    }
+  }
+
+  pub fn set(&mut self, (x, y): (i32, i32), ground: Ground) {
+    if let Some(i) = self.index(x, y) {
+      self.cells[i] = ground;
+    }
+  }
+
+  /// the ground of a cell, off the map counts as grass
+  pub fn ground(&self, (x, y): (i32, i32)) -> Ground {
+    self.index(x, y).map_or(Ground::Grass, |i| self.cells[i])
+  }
+
+  fn node_index(&self, x: i32, y: i32) -> Option<usize> {
+    let (w, h) = (self.grid.size.0 as i32 + 1, self.grid.size.1 as i32 + 1);
+
+    if x < 0 || y < 0 || x >= w || y >= h {
+      None
+    } else {
+      Some((y * w + x) as usize)
+    }
+  }
+
+  /// sets the height of the node at the top left corner of cell `(x, y)`
+  pub fn set_height(&mut self, (x, y): (i32, i32), height: f32) {
+    if let Some(i) = self.node_index(x, y) {
+      self.heights[i] = height;
+    }
+  }
+
+  /// the height of a node, the edge of the map carries on flat
+  pub fn height(&self, (x, y): (i32, i32)) -> f32 {
+    let (w, h) = (self.grid.size.0 as i32, self.grid.size.1 as i32);
+    let i = self.node_index(x.max(0).min(w), y.max(0).min(h)).expect("clamped onto the map");
+
+    self.heights[i]
+  }
+
+  /// how high up a point in the world is, in between the nodes around it
+  pub fn elevation(&self, pos: Pos) -> f32 {
+    let cell = self.grid.cell_size as f32;
+    let (x, y) = pos.to_float();
+    let (fx, fy) = (x / cell, y / cell);
+    let (nx, ny) = (fx.floor() as i32, fy.floor() as i32);
+    let (tx, ty) = (fx - nx as f32, fy - ny as f32);
+
+    let top = self.height((nx, ny)) + (self.height((nx + 1, ny)) - self.height((nx, ny))) * tx;
+    let bottom = self.height((nx, ny + 1)) + (self.height((nx + 1, ny + 1)) - self.height((nx, ny + 1))) * tx;
+
+    top + (bottom - top) * ty
+  }
+
+  /// the ground under a point in the world
+  pub fn at(&self, pos: Pos) -> Ground {
+    let cell = self.grid.cell_size as f32;
+    let (x, y) = pos.to_float();
+
+    self.ground(((x / cell).floor() as i32, (y / cell).floor() as i32))
+  }
+
+  pub fn draw(&self, ctx: &mut Context) -> GameResult<()> {
+    let cell = self.grid.cell_size as f32;
+    let w = self.grid.size.0 as usize;
+
+    for (i, ground) in self.cells.iter().enumerate() {
+      match ground {
+        Ground::Grass => continue,
+        Ground::Hills => graphics::set_color(ctx, [0.6, 0.5, 0.3, 0.4].into())?,
+        Ground::Water => graphics::set_color(ctx, [0.2, 0.4, 0.9, 0.5].into())?,
+      }
+
+      let (x, y) = ((i % w) as f32 * cell, (i / w) as f32 * cell);
+      graphics::rectangle(ctx, DrawMode::Fill, Rect::new(x, y, cell, cell))?;
+    }
+
+    Ok(())
+  }
+}
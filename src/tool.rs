@@ -0,0 +1,63 @@
+use input::{Action, Bindings};
+
+/// what a left click does
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq, Deserialize)]
+pub enum Tool {
+  Select,
+  Track,
+  Demolish,
+  Signal,
+  Station,
+  Industry,
+  Train,
+  Limit,
+  Wires,
+  Depot,
+}
+
+impl Tool {
+  /// in the order they show up in the toolbar
+  pub const ALL: [Tool; 10] = [
+    Tool::Select,
+    Tool::Track,
+    Tool::Demolish,
+    Tool::Signal,
+    Tool::Station,
+    Tool::Industry,
+    Tool::Train,
+    Tool::Limit,
+    Tool::Wires,
+    Tool::Depot,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Tool::Select => "select",
+      Tool::Track => "track",
+      Tool::Demolish => "demolish",
+      Tool::Signal => "signal",
+      Tool::Station => "station",
+      Tool::Industry => "industry",
+      Tool::Train => "train",
+      Tool::Limit => "limit",
+      Tool::Wires => "wires",
+      Tool::Depot => "depot",
+    }
+  }
+
+  /// a single line listing every tool with the active one in brackets
+  pub fn toolbar(active: Tool, bindings: &Bindings) -> String {
+    Tool::ALL.iter()
+        .map(|&tool| {
+          let key = bindings.name(Action::Pick(tool));
+
+          if tool == active {
+            format!("[{} {}]", key, tool.name())
+          } else {
+            format!(" {} {} ", key, tool.name())
+          }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+  }
+}
@@ -0,0 +1,131 @@
+use rand::Rng;
+
+use ggez::{
+  Context,
+  graphics::{self, DrawMode, Font, Point2, Rect, Text},
+  GameResult,
+};
+
+use cargo::Cargo;
+use path::Pos;
+use station::CATCHMENT;
+
+// how many people live in a building
+const PEOPLE_PER_BUILDING: u32 = 50;
+// how many people it takes for a passenger or a bag of mail every second
+const PASSENGER_PEOPLE: f32 = 100.;
+const MAIL_PEOPLE: f32 = 400.;
+// attempts at finding a free spot for a new building
+const BUILD_TRIES: usize = 20;
+
+/// people living in a cluster of buildings, they want to go places
+pub struct Town {
+  pub name: String,
+  pub population: u32,
+  buildings: Vec<(i32, i32)>,
+  // passengers and mail that haven't made a whole one yet
+  demand: [f32; 2],
+}
+
+impl Town {
+  /// a town with a single building at `cell`, use `grow` to fill it up
+  pub fn new(name: String, cell: (i32, i32), population: u32) -> Self {
+    Town {
+      name,
+      population,
+      buildings: vec![cell],
+      demand: [0., 0.],
+    }
+  }
+
+  pub fn covers(&self, cell: (i32, i32)) -> bool {
+    self.buildings.contains(&cell)
+  }
+
+  pub fn buildings(&self) -> &[(i32, i32)] {
+    &self.buildings
+  }
+
+  /// the middle of the first building
+  pub fn center(&self, cell_size: i16) -> Pos {
+    let (x, y) = self.buildings[0];
+    let cell = cell_size as i32;
+
+    Pos(x * cell + cell / 2, y * cell + cell / 2)
+  }
+
+  /// whether a station at `pos` is close enough to any of the buildings
+  pub fn serves(&self, pos: Pos, cell_size: i16) -> bool {
+    let cell = cell_size as f32;
+    let (x, y) = pos.to_float();
+
+    self.buildings.iter().any(|&(bx, by)| {
+      let (cx, cy) = ((bx as f32 + 0.5) * cell, (by as f32 + 0.5) * cell);
+      ((cx - x).powi(2) + (cy - y).powi(2)).sqrt() <= CATCHMENT * cell
+    })
+  }
+
+  /// called once a second, returns the passengers and mail that want to leave
+  pub fn produce(&mut self) -> Vec<(Cargo, u32)> {
+    let rates = [
+      (Cargo::Passengers, self.population as f32 / PASSENGER_PEOPLE),
+      (Cargo::Mail, self.population as f32 / MAIL_PEOPLE),
+    ];
+
+    rates.iter().zip(self.demand.iter_mut())
+        .filter_map(|(&(cargo, rate), demand)| {
+          *demand += rate;
+
+          let amount = demand.floor();
+          *demand -= amount;
+
+          if amount >= 1. { Some((cargo, amount as u32)) } else { None }
+        })
+        .collect()
+  }
+
+  /// adds people and puts up buildings for them on cells `free` allows
+  pub fn grow<R: Rng, F: Fn((i32, i32)) -> bool>(&mut self, rng: &mut R, people: u32, free: F) {
+    self.population += people;
+
+    let wanted = (self.population / PEOPLE_PER_BUILDING).max(1) as usize;
+
+    while self.buildings.len() < wanted {
+      let spot = (0..BUILD_TRIES)
+          .map(|_| {
+            // next to one of the buildings that are already there
+            let (x, y) = self.buildings[rng.gen_range(0, self.buildings.len())];
+            let (dx, dy) = [(1, 0), (-1, 0), (0, 1), (0, -1)][rng.gen_range(0, 4)];
+            (x + dx, y + dy)
+          })
+          .find(|&spot| !self.buildings.contains(&spot) && free(spot));
+
+      match spot {
+        Some(spot) => self.buildings.push(spot),
+        // boxed in, the people will have to squeeze
+        None => break,
+      }
+    }
+  }
+
+  pub fn draw(&self, ctx: &mut Context, font: &Font, cell_size: i16) -> GameResult<()> {
+    let cell = cell_size as f32;
+    let margin = cell / 6.;
+
+    for &(x, y) in self.buildings.iter() {
+      let rect = Rect::new(x as f32 * cell + margin, y as f32 * cell + margin, cell - 2. * margin, cell - 2. * margin);
+
+      graphics::set_color(ctx, [0.7, 0.4, 0.3, 0.9].into())?;
+      graphics::rectangle(ctx, DrawMode::Fill, rect)?;
+
+      graphics::set_color(ctx, [0.0, 0.0, 0.0, 1.0].into())?;
+      graphics::rectangle(ctx, DrawMode::Line(1.), rect)?;
+    }
+
+    let &(x, y) = self.buildings.first().expect("towns have at least one building");
+    let label = Text::new(ctx, &format!("{} ({})", self.name, self.population), font)?;
+    let dest = Point2::new(x as f32 * cell, y as f32 * cell - label.height() as f32);
+
+    graphics::draw(ctx, &label, dest, 0.)
+  }
+}
@@ -1,4 +1,9 @@
-use rand::{Rng, thread_rng};
+pub mod spawn;
+pub mod traction;
+
+use std::mem;
+
+use rand::Rng;
 
 use ggez::{
   Context,
@@ -8,27 +13,113 @@ use ggez::{
     Color,
   },
   GameResult,
-  timer::{
-    get_delta,
-    duration_to_f64,
-  },
 };
 
-use path::track::{
-  Track,
-  TrackPiece,
+use cargo::{Cargo, Parcel};
+use path::{
+  track::{
+    Track,
+    TrackPiece,
+  },
+  Pos,
 };
+use terrain::Terrain;
+
+use self::traction::Traction;
+
+// how close a click has to be to a segment to select the train
+const HIT_RADIUS: f32 = 12.;
+// how close segments of two trains can get before they count as crashed
+const COLLISION_RADIUS: f32 = 8.;
+// how much cargo a single car holds
+const CAR_CAPACITY: u32 = 30;
+// between the cars of a train that only has the one
+const SPACING: f32 = 10.;
+// how quickly trains pick up speed and brake, in pixels per second squared
+const ACCELERATION: f32 = 100.;
+const BRAKING: f32 = 150.;
+// what gravity adds to that going downhill and takes off going up, for a gradient of one
+const GRAVITY: f32 = 1000.;
+// trains always manage at least this much of their acceleration, however steep it gets
+const MIN_PULL: f32 = 0.1;
+// what a car weighs empty and every unit of cargo on top of that
+const CAR_WEIGHT: u32 = 20;
+const CARGO_WEIGHT: u32 = 1;
+// how much each unit of weight slows a train down on a gradient of one, and how much
+// quicker than its top speed running downhill lets it go
+const CLIMB: f32 = 0.1;
+const DESCENT: f32 = 5.;
+// how far short of the buffers trains come to a stop
+const BUFFER_GAP: f32 = 4.;
+// the slowest trains go when they roll up to the buffers
+const CREEP: f32 = 10.;
+// running into the buffers any faster than this wrecks the train
+const CRASH_SPEED: f32 = 40.;
+// how far over a turn's limit a train can go before it comes off
+const OVERSPEED: f32 = 1.1;
+// trains meeting slower than this between them couple up instead of crashing
+pub const COUPLE_SPEED: f32 = 20.;
+// how far a train goes before a depot services it when it calls there, and how far before
+// it wears out and only manages part of its top speed
+const SERVICE_DUE: f32 = 15000.;
+const WORN_OUT: f32 = 30000.;
+const WORN_SPEED: f32 = 0.75;
+
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
+pub enum Status {
+  Running,
+  Stopped,
+  // ran into the buffers too fast, it's not going anywhere anymore
+  Crashed,
+}
+
+impl Status {
+  pub fn name(&self) -> &'static str {
+    match self {
+      Status::Running => "running",
+      Status::Stopped => "stopped",
+      Status::Crashed => "crashed",
+    }
+  }
+}
+
+/// what trains go by on a single piece of track
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
+pub struct Rules {
+  // the fastest they're allowed to take it
+  pub limit: f32,
+  // how much it climbs from start to end
+  pub grade: f32,
+  // whether electric trains can run on it
+  pub wired: bool,
+}
 
 pub struct Train {
   segments: Vec<Segment>,
   colour: Color,
+  status: Status,
+  // wagons left standing by a split don't have one and can't move by themselves
+  traction: Option<Traction>,
+  // how fast it's going right now, the segments only know which way and how fast it can go,
+  // new trains pull away from a standstill
+  current: f32,
+  cargo: Vec<Parcel>,
+  capacity: u32,
+  // ticks left standing at a station
+  dwell: u32,
+  // the station it stopped at last, so it doesn't stop there again right away
+  last_station: Option<usize>,
+  // how far it went since it was last serviced
+  wear: f32,
+  // on its way back to a depot to be put away
+  bound: bool,
 }
 
 impl Train {
-  pub fn new(speed: f32, track: usize, dist: f32, (seg_n, seg_dist, seg_len): (usize, f32, f32)) -> Self {
-    let mut rnd = thread_rng();
-
-    let colour: Color = [rnd.gen_range(0.0, 1.0), rnd.gen_range(0.0, 1.0), rnd.gen_range(0.0, 1.0), 1.0].into();
+  /// `speed` is cut down to what `traction` can manage
+  pub fn new<R: Rng>(rnd: &mut R, speed: f32, track: usize, dist: f32, (seg_n, seg_dist, seg_len): (usize, f32, f32), traction: Traction) -> Self {
+    let colour = random_colour(rnd);
+    let speed = speed.signum() * speed.abs().min(traction.max_speed());
 
     let mut segments = Vec::new();
 
@@ -44,27 +135,664 @@ impl Train {
     Train {
       segments,
       colour,
+      status: Status::Running,
+      traction: Some(traction),
+      current: 0.,
+      cargo: Vec::new(),
+      capacity: seg_n as u32 * CAR_CAPACITY,
+      dwell: 0,
+      last_station: None,
+      wear: 0.,
+      bound: false,
     }
   }
 
-  pub fn update(&mut self, ctx: &mut Context, tracks: &Vec<Track>) {
-    let delta = duration_to_f64(get_delta(ctx)) as f32;
+  /// the segment leading in the direction of travel, segments pass each other when
+  /// they bounce off the end of the line so it's not always the same one
+  fn head_index(&self) -> usize {
+    let first = self.segments.first().expect("trains have at least one segment");
+    let forward = first.speed >= 0.;
+
+    let ahead = |a: &Segment, b: &Segment| {
+      let further = a.track > b.track || (a.track == b.track && a.dist > b.dist);
+      if forward { further } else { !further }
+    };
 
+    (1..self.segments.len()).fold(0, |head, i| {
+      if ahead(&self.segments[i], &self.segments[head]) { i } else { head }
+    })
+  }
+
+  fn head(&self) -> &Segment {
+    &self.segments[self.head_index()]
+  }
+
+  pub fn head_pos(&self) -> (f32, f32) {
+    self.head().pos
+  }
+
+  /// the piece the head is on
+  pub fn track(&self) -> usize {
+    self.head().track
+  }
+
+  pub fn speed(&self) -> f32 {
+    self.current
+  }
+
+  pub fn top_speed(&self) -> f32 {
+    self.head().speed.abs()
+  }
+
+  /// the cars and everything in them
+  pub fn weight(&self) -> u32 {
+    self.cars() as u32 * CAR_WEIGHT + self.cargo.iter().map(|parcel| parcel.amount).sum::<u32>() * CARGO_WEIGHT
+  }
+
+  /// how steep it is under the train on average going the way it's going
+  pub fn grade(&self, rules: &[Rules]) -> f32 {
+    self.segments.iter()
+        .map(|seg| if seg.speed >= 0. { rules[seg.track].grade } else { -rules[seg.track].grade })
+        .sum::<f32>() / self.segments.len() as f32
+  }
+
+  /// how fast it can go on `grade`, heavy trains slow right down going uphill and
+  /// every train runs faster than its top speed downhill
+  fn running_speed(&self, grade: f32) -> f32 {
+    let top = if self.wear >= WORN_OUT { self.top_speed() * WORN_SPEED } else { self.top_speed() };
+
+    if grade > 0. {
+      top / (1. + grade * self.weight() as f32 * CLIMB)
+    } else {
+      top * (1. - grade * DESCENT)
+    }
+  }
+
+  fn acceleration(grade: f32) -> f32 {
+    (ACCELERATION - GRAVITY * grade).max(ACCELERATION * MIN_PULL)
+  }
+
+  /// the furthest the train can get in `delta` seconds
+  pub fn reach(&self, rules: &[Rules], delta: f32) -> f32 {
+    let grade = self.grade(rules);
+
+    (self.current + Train::acceleration(grade) * delta).min(self.running_speed(grade).max(self.current)) * delta
+  }
+
+  /// how far ahead it has to know about a signal or a crossing to brake for it in time,
+  /// going as fast as it can after the next `delta` seconds
+  pub fn sight(&self, rules: &[Rules], delta: f32) -> f32 {
+    let reach = self.reach(rules, delta);
+    let speed = reach / delta;
+
+    reach + speed * speed / (2. * BRAKING)
+  }
+
+  pub fn traction(&self) -> Option<Traction> {
+    self.traction
+  }
+
+  /// whether it can run on a piece that is `wired` or not
+  fn powered(&self, wired: bool) -> bool {
+    wired || !self.traction.map_or(false, |traction| traction.needs_wires())
+  }
+
+  /// whether it's on its way anywhere right now
+  pub fn moving(&self) -> bool {
+    self.status == Status::Running && self.current > 0.
+  }
+
+  /// the pieces coming up and how far off they start, and how far the head can go before
+  /// the line ends or the train can't go any further on it, not looking much further than `limit`
+  fn look_ahead(&self, tracks: &Vec<Track>, rules: &[Rules], limit: f32) -> (Vec<(usize, f32)>, f32) {
+    let head = self.head();
+    let forward = head.speed >= 0.;
+
+    let mut pieces = Vec::new();
+    let mut track = head.track;
+    let mut room = if forward { tracks[track].len() - head.dist } else { head.dist };
+
+    while room < limit {
+      let next = if forward { next_track(tracks, track) } else { prev_track(tracks, track) };
+
+      match next {
+        Some(next) if self.powered(rules[next].wired) => {
+          pieces.push((next, room));
+          room += tracks[next].len();
+          track = next;
+        }
+        _ => break,
+      }
+    }
+
+    (pieces, room)
+  }
+
+  pub fn status(&self) -> Status {
+    self.status
+  }
+
+  pub fn cars(&self) -> usize {
+    self.segments.len() / 2
+  }
+
+  pub fn standing(&self) -> bool {
+    self.dwell > 0
+  }
+
+  /// counts down the time at a station, returns whether it's still standing
+  pub fn wait(&mut self) -> bool {
+    if self.dwell > 0 {
+      self.dwell -= 1;
+      true
+    } else {
+      false
+    }
+  }
+
+  pub fn last_station(&self) -> Option<usize> {
+    self.last_station
+  }
+
+  /// stands at `station` for `dwell` ticks
+  pub fn arrive(&mut self, station: usize, dwell: u32) {
+    self.last_station = Some(station);
+    self.dwell = dwell;
+  }
+
+  /// for when station numbers change
+  pub fn forget_station(&mut self) {
+    self.last_station = None;
+  }
+
+  /// hands over everything that wasn't loaded at `at` and is wanted there
+  pub fn unload<F: Fn(Cargo) -> bool>(&mut self, at: Pos, accepts: F) -> Vec<Parcel> {
+    let (delivered, kept): (Vec<Parcel>, Vec<Parcel>) = self.cargo.drain(..)
+        .partition(|parcel| parcel.from != at && accepts(parcel.cargo));
+    self.cargo = kept;
+
+    delivered
+  }
+
+  pub fn load(&mut self, parcel: Parcel) {
+    self.cargo.push(parcel);
+  }
+
+  /// how much more cargo fits
+  pub fn space(&self) -> u32 {
+    self.capacity - self.cargo.iter().map(|parcel| parcel.amount).sum::<u32>()
+  }
+
+  pub fn toggle_stop(&mut self) {
+    self.status = match self.status {
+      Status::Running => Status::Stopped,
+      Status::Stopped if self.traction.is_some() => Status::Running,
+      status => status,
+    };
+  }
+
+  pub fn reverse(&mut self) {
+    if self.status == Status::Crashed {
+      return;
+    }
+
+    for seg in self.segments.iter_mut() {
+      seg.speed = -seg.speed;
+    }
+  }
+
+  pub fn hit(&self, pos: Pos) -> bool {
+    let (x, y) = pos.to_float();
+
+    self.segments.iter().any(|seg| {
+      ((seg.pos.0 - x).powi(2) + (seg.pos.1 - y).powi(2)).sqrt() <= HIT_RADIUS
+    })
+  }
+
+  pub fn touches(&self, other: &Train) -> bool {
+    self.segments.iter().any(|seg| other.segments.iter().any(|o| {
+      ((seg.pos.0 - o.pos.0).powi(2) + (seg.pos.1 - o.pos.1).powi(2)).sqrt() <= COLLISION_RADIUS
+    }))
+  }
+
+  /// leaves everything behind the first `keep` cars, counted from the head, standing on
+  /// its own without an engine and returns it, only trains that stand still can split
+  pub fn split<R: Rng>(&mut self, rnd: &mut R, keep: usize) -> Option<Train> {
+    if keep == 0 || keep >= self.cars() || self.moving() {
+      return None;
+    }
+
+    // the segments run from one end of the train to the other, the head is at one of them
+    let rest = if self.head_index() < self.segments.len() / 2 {
+      self.segments.split_off(keep * 2)
+    } else {
+      let cut = self.segments.len() - keep * 2;
+      self.segments.drain(..cut).collect()
+    };
+
+    // the front cars stay loaded as far as they go, the rest of the load stays behind
+    let capacity = keep as u32 * CAR_CAPACITY;
+    let mut room = capacity;
+    let (mut kept, mut left) = (Vec::new(), Vec::new());
+
+    for parcel in self.cargo.drain(..) {
+      let taken = parcel.amount.min(room);
+      room -= taken;
+
+      if taken > 0 {
+        kept.push(Parcel { amount: taken, ..parcel });
+      }
+      if taken < parcel.amount {
+        left.push(Parcel { amount: parcel.amount - taken, ..parcel });
+      }
+    }
+
+    let wagons = Train {
+      segments: rest,
+      colour: random_colour(rnd),
+      status: Status::Stopped,
+      traction: None,
+      current: 0.,
+      cargo: left,
+      capacity: self.capacity - capacity,
+      dwell: 0,
+      last_station: None,
+      wear: self.wear,
+      bound: false,
+    };
+
+    self.cargo = kept;
+    self.capacity = capacity;
+
+    Some(wagons)
+  }
+
+  /// takes on `other`'s cars and everything in them, whichever of the two has an engine
+  /// carries on the way it was going but has to pull away again, at least one of them has
+  /// to be just wagons
+  pub fn couple(&mut self, mut other: Train) {
+    if self.traction.is_none() && other.traction.is_some() {
+      mem::swap(self, &mut other);
+    }
+
+    let speed = self.head().speed;
+    let mut segments = other.segments;
+
+    for seg in segments.iter_mut() {
+      seg.speed = speed;
+    }
+
+    // keep the segments in order along the line
+    let after = {
+      let (first, last) = (&segments[0], &self.segments[self.segments.len() - 1]);
+      first.track > last.track || (first.track == last.track && first.dist > last.dist)
+    };
+
+    if after {
+      self.segments.append(&mut segments);
+    } else {
+      segments.append(&mut self.segments);
+      self.segments = segments;
+    }
+
+    self.cargo.append(&mut other.cargo);
+    self.capacity += other.capacity;
+    self.current = 0.;
+  }
+
+  /// whether one of the two trains has the other on the line ahead, trains only couple up
+  /// end to end and not where lines cross
+  pub fn in_line_with(&self, tracks: &Vec<Track>, other: &Train) -> bool {
+    self.gap_to(tracks, other).is_some() || other.gap_to(tracks, self).is_some()
+  }
+
+  /// how far ahead of the head the closest part of `other` is, if it's on the line within
+  /// stopping distance
+  pub fn gap_to(&self, tracks: &Vec<Track>, other: &Train) -> Option<f32> {
+    let head = self.head();
+    let forward = head.speed >= 0.;
+    let fastest = self.current.max(self.top_speed());
+    let limit = fastest * fastest / (2. * BRAKING) + fastest;
+
+    let mut track = head.track;
+    // how far ahead of the head the piece starts, going the way the train goes
+    let mut base = if forward { -head.dist } else { head.dist - tracks[track].len() };
+
+    while base < limit {
+      let len = tracks[track].len();
+      let gap = other.segments.iter()
+          .filter(|seg| seg.track == track)
+          .map(|seg| base + if forward { seg.dist } else { len - seg.dist })
+          .filter(|&gap| gap >= 0.)
+          .fold(None, |closest: Option<f32>, gap| Some(closest.map_or(gap, |closest| closest.min(gap))));
+
+      if gap.is_some() {
+        return gap;
+      }
+
+      base += len;
+      track = match if forward { next_track(tracks, track) } else { prev_track(tracks, track) } {
+        Some(next) => next,
+        None => return None,
+      };
+    }
+
+    None
+  }
+
+  pub fn service_due(&self) -> bool {
+    self.wear >= SERVICE_DUE
+  }
+
+  /// how badly it needs servicing, for the panels
+  pub fn condition(&self) -> &'static str {
+    if self.wear >= WORN_OUT {
+      "worn out"
+    } else if self.service_due() {
+      "service due"
+    } else {
+      "ok"
+    }
+  }
+
+  /// stands for `dwell` ticks while it gets looked after, then it's as good as new
+  pub fn service(&mut self, dwell: u32) {
+    self.wear = 0.;
+    self.dwell = dwell;
+  }
+
+  pub fn bound(&self) -> bool {
+    self.bound
+  }
+
+  /// sends the train back to a depot or calls that off again
+  pub fn toggle_bound(&mut self) {
+    self.bound = !self.bound;
+  }
+
+  /// how far along the line each segment is from the end of the train closest to the start
+  pub fn layout(&self, tracks: &Vec<Track>) -> Vec<f32> {
+    let spots = self.segments.iter().map(|seg| along(tracks, seg.track, seg.dist)).collect::<Vec<f32>>();
+    let start = spots.iter().cloned().fold(::std::f32::INFINITY, f32::min);
+
+    spots.iter().map(|spot| spot - start).collect()
+  }
+
+  /// puts the train back on the line with the first segment `dist` along `track` and the
+  /// others `offsets` on from it, going forward or backward along the line
+  pub fn rerail(&mut self, tracks: &Vec<Track>, track: usize, dist: f32, forward: bool, offsets: &[f32]) {
+    let speed = if forward { self.top_speed() } else { -self.top_speed() };
+
+    for (seg, offset) in self.segments.iter_mut().zip(offsets.iter()) {
+      *seg = Segment::new(speed, track, dist + offset);
+    }
+
+    self.status = Status::Running;
+    self.current = 0.;
+    self.dwell = 0;
+    self.bound = false;
+    self.place(tracks);
+  }
+
+  /// couples an empty car on behind a train standing on the line, spaced like the others, as
+  /// long as the line carries on far enough behind it, returns whether there was room
+  pub fn add_car(&mut self, tracks: &Vec<Track>) -> bool {
+    if self.moving() {
+      return false;
+    }
+
+    let offsets = self.layout(tracks);
+    let n = offsets.len();
+    let (head, tail) = if self.head_index() == 0 { (0, n - 1) } else { (n - 1, 0) };
+
+    // the new car goes on past the tail, away from the head
+    let back = if offsets[tail] >= offsets[head] { 1. } else { -1. };
+    let car = (offsets[1] - offsets[0]).abs();
+    let gap = if n > 2 { (offsets[2] - offsets[1]).abs() } else { SPACING };
+
+    let (track, speed) = (self.segments[tail].track, self.segments[tail].speed);
+    let at = along(tracks, track, self.segments[tail].dist);
+    let (near, far) = match (on_line(tracks, track, at + back * gap), on_line(tracks, track, at + back * (gap + car))) {
+      (Some(near), Some(far)) => (near, far),
+      _ => return false,
+    };
+
+    let place = |(track, dist): (usize, f32)| {
+      let mut seg = Segment::new(speed, track, dist);
+      seg.update(tracks, 0.);
+      seg.prev_pos = seg.pos;
+      seg
+    };
+    let (near, far) = (place(near), place(far));
+
+    // the segments keep running from one end of the train to the other
+    if tail == 0 {
+      self.segments.insert(0, near);
+      self.segments.insert(0, far);
+    } else {
+      self.segments.push(near);
+      self.segments.push(far);
+    }
+
+    self.capacity += CAR_CAPACITY;
+    true
+  }
+
+  /// takes the car at the back off a train standing on the line, as long as there's one left
+  /// and the load still fits, returns whether it did
+  pub fn remove_car(&mut self) -> bool {
+    if self.moving() || self.cars() <= 1 || self.space() < CAR_CAPACITY {
+      return false;
+    }
+
+    if self.head_index() == 0 {
+      let n = self.segments.len();
+      self.segments.truncate(n - 2);
+    } else {
+      self.segments.drain(..2);
+    }
+
+    self.capacity -= CAR_CAPACITY;
+    true
+  }
+
+  /// whether the train stretches over `dist` on `track`
+  pub fn covers(&self, track: usize, dist: f32) -> bool {
+    let before = |seg: &Segment| seg.track < track || (seg.track == track && seg.dist <= dist);
+    let after = |seg: &Segment| seg.track > track || (seg.track == track && seg.dist >= dist);
+
+    self.segments.iter().any(before) && self.segments.iter().any(after)
+  }
+
+  /// whether any part of the train is within `radius` of `pos`
+  pub fn near(&self, pos: Pos, radius: f32) -> bool {
+    let (x, y) = pos.to_float();
+
+    self.segments.iter().any(|seg| ((seg.pos.0 - x).powi(2) + (seg.pos.1 - y).powi(2)).sqrt() <= radius)
+  }
+
+  pub fn on_track(&self, track: usize) -> bool {
+    self.segments.iter().any(|seg| seg.track == track)
+  }
+
+  /// shifts the segments back after the track at `track` was taken out
+  pub fn track_removed(&mut self, track: usize) {
+    for seg in self.segments.iter_mut() {
+      if seg.track > track {
+        seg.track -= 1;
+      }
+    }
+  }
+
+  /// how far the head has to go to reach `dist` on `track`, if that's ahead of it on the line
+  pub fn distance_to(&self, tracks: &Vec<Track>, track: usize, dist: f32) -> Option<f32> {
+    let head = self.head();
+    let forward = head.speed >= 0.;
+    let mut at = head.track;
+    let mut gone = if forward { -head.dist } else { head.dist - tracks[at].len() };
+
+    loop {
+      if at == track {
+        let left = gone + if forward { dist } else { tracks[at].len() - dist };
+
+        if left >= 0. {
+          return Some(left);
+        }
+      }
+
+      gone += tracks[at].len();
+      at = if forward { next_track(tracks, at)? } else { prev_track(tracks, at)? };
+    }
+  }
+
+  /// lines for the inspector panel
+  pub fn inspect(&self, tracks: &Vec<Track>, terrain: &Terrain) -> Vec<String> {
+    let head = self.head();
+    let track = &tracks[head.track];
+    let gradient = if head.speed >= 0. { track.gradient(terrain) } else { -track.gradient(terrain) };
+
+    let load = if self.cargo.is_empty() {
+      "empty".to_owned()
+    } else {
+      self.cargo.iter()
+          .map(|parcel| format!("{} {}", parcel.amount, parcel.cargo.name()))
+          .collect::<Vec<String>>()
+          .join(", ")
+    };
+
+    vec![
+      format!("status: {}", if self.standing() { "loading" } else { self.status.name() }),
+      format!("engine: {}", self.traction.map_or("none", |traction| traction.name())),
+      format!("speed: {:.0}", self.speed()),
+      format!("track: {} ({})", head.track, track.name()),
+      format!("distance: {:.1} / {:.1}", head.dist, track.len()),
+      format!("gradient: {:+.1}%", gradient * 100.),
+      format!("last stop: {}", self.last_station.map_or("none".to_owned(), |station| format!("station #{}", station))),
+      format!("heading for: {}", if self.bound { "a depot" } else { "the next station" }),
+      format!("condition: {}", self.condition()),
+      format!("cargo: {} ({} free)", load, self.space()),
+      format!("weight: {}", self.weight()),
+    ]
+  }
+
+  /// moves the train along, keeping to the speed limit of every piece and braking for the
+  /// buffers, it turns around once it stands in front of them, gradients slow it down going
+  /// uphill and speed it up going down, electric trains treat the end of the wires like buffers
+  ///
+  /// it creeps up to wagons standing `couple` ahead so it can couple up to them and brakes so
+  /// it stops `hold` ahead, with `derail` a train that takes a turn faster than the turn allows
+  /// comes off, returns whether it turned around
+  pub fn update(&mut self, tracks: &Vec<Track>, rules: &[Rules], couple: Option<f32>, hold: Option<f32>, derail: bool, delta: f32) -> bool {
+    let top = self.top_speed();
+    let grade = self.grade(rules);
+    let running = self.running_speed(grade);
+    let mut travel = 0.;
+    let mut turned = false;
+
+    if self.status == Status::Running && delta > 0. && top > 0. {
+      let fastest = running.max(self.current);
+      let (ahead, room) = self.look_ahead(tracks, rules, fastest * fastest / (2. * BRAKING) + fastest);
+      let room = room - BUFFER_GAP;
+      // the wagons get touched a little before the head gets to them, and it only brakes after
+      // this step's move so it has to leave room for that too
+      let stop = couple.map_or(room, |gap| room.min(gap - COLLISION_RADIUS - self.current * delta));
+      let stop = hold.map_or(stop, |dist| stop.min(dist - self.current * delta));
+
+      if room <= 0. && self.current == 0. {
+        self.reverse();
+        turned = true;
+      } else {
+        // as fast as it can go while still slowing down in time for the buffers and
+        // every limit coming up, and keeping to the ones it's on
+        let limit = |track: usize| rules[track].limit;
+        let allowed = ahead.iter()
+            .map(|&(track, dist)| (limit(track) * limit(track) + 2. * BRAKING * dist).sqrt())
+            .chain(self.segments.iter().map(|seg| limit(seg.track)))
+            .fold((2. * BRAKING * stop.max(0.)).sqrt().max(CREEP).min(running), f32::min);
+
+        self.current = if self.current > allowed {
+          (self.current - BRAKING * delta).max(allowed)
+        } else {
+          (self.current + Train::acceleration(grade) * delta).min(allowed)
+        };
+
+        travel = self.current * delta;
+
+        if travel >= room {
+          if self.current > CRASH_SPEED {
+            self.status = Status::Crashed;
+          }
+
+          travel = room.max(0.);
+          self.current = 0.;
+        }
+      }
+    } else {
+      // right in front of a signal or a crossing it braked for, loading at a station, or stopped,
+      // it stands still and has to pull away again
+      self.current = 0.;
+    }
+
+    self.wear += travel;
+
+    // segments move at their top speed, so they get the time it takes at that
+    let delta = if top > 0. { travel / top } else { 0. };
 
     for seg in self.segments.iter_mut() {
       seg.update(tracks, delta);
     }
+
+    // nothing but the limits put up slows a train down for a turn when it can come off one
+    let speed = self.current;
+    let too_fast = self.segments.iter().any(|seg| tracks[seg.track].max_speed().map_or(false, |max| speed > max * OVERSPEED));
+
+    if derail && too_fast {
+      self.status = Status::Crashed;
+    }
+
+    turned
+  }
+
+  /// puts the segments in place without moving them
+  pub fn place(&mut self, tracks: &Vec<Track>) {
+    for seg in self.segments.iter_mut() {
+      seg.update(tracks, 0.);
+      seg.prev_pos = seg.pos;
+    }
+  }
+
+  /// `alpha` is how far along the frame is between the last and the next tick
+  pub fn draw(&mut self, ctx: &mut Context, alpha: f32) -> GameResult<()> {
+    let colour = if self.status == Status::Crashed { [0.3, 0.3, 0.3, 1.0].into() } else { self.colour };
+    self.draw_coloured(ctx, colour, alpha)
+  }
+
+  pub fn draw_highlight(&self, ctx: &mut Context, alpha: f32) -> GameResult<()> {
+    graphics::set_color(ctx, [1.0, 0.8, 0.0, 1.0].into())?;
+
+    for seg in self.segments.iter() {
+      let pos = seg.lerp_pos(alpha);
+      graphics::circle(ctx, graphics::DrawMode::Fill, Point2::new(pos.0, pos.1), 9., 0.2)?;
+    }
+
+    for conn in self.segments.chunks(2) {
+      let (start, end) = (conn[0].lerp_pos(alpha), conn[1].lerp_pos(alpha));
+      let (start_p, end_p) = (Point2::new(start.0, start.1), Point2::new(end.0, end.1));
+      graphics::line(ctx, &[start_p, end_p], 16.)?;
+    }
+
+    Ok(())
   }
 
-  pub fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
-    graphics::set_color(ctx, self.colour)?;
+  pub fn draw_coloured(&mut self, ctx: &mut Context, colour: Color, alpha: f32) -> GameResult<()> {
+    graphics::set_color(ctx, colour)?;
 
     for seg in self.segments.iter_mut() {
-      seg.draw(ctx)?;
+      seg.draw(ctx, alpha)?;
     }
 
     for conn in self.segments.chunks(2) {
-      let (start, end) = (conn[0].pos, conn[1].pos);
+      let (start, end) = (conn[0].lerp_pos(alpha), conn[1].lerp_pos(alpha));
       let (start_p, end_p) = (Point2::new(start.0, start.1), Point2::new(end.0, end.1));
       graphics::line(ctx, &[start_p, end_p], 10.)?;
     }
@@ -78,6 +806,8 @@ pub struct Segment {
   track: usize,
   dist: f32,
   pos: (f32, f32),
+  // position at the previous tick, for drawing in between ticks
+  prev_pos: (f32, f32),
 }
 
 impl Segment {
@@ -87,10 +817,13 @@ impl Segment {
       track,
       dist,
       pos: (0., 0.),
+      prev_pos: (0., 0.),
     }
   }
 
   pub fn update(&mut self, tracks: &Vec<Track>, delta: f32) {
+    self.prev_pos = self.pos;
+
     let mut track = tracks.get(self.track).expect("tracks should have the current one");
     let mut len = track.len();
 
@@ -99,10 +832,10 @@ impl Segment {
     while self.dist > len || self.dist < 0. {
       if self.dist > len {
         self.dist = self.dist - len;
-        if let Some(trc) = tracks.get(self.track + 1) {
-          track = trc;
+        if let Some(next) = next_track(tracks, self.track) {
+          track = &tracks[next];
           len = track.len();
-          self.track += 1;
+          self.track = next;
         } else {
           self.dist = len - self.dist;
           self.speed = -self.speed;
@@ -111,10 +844,10 @@ impl Segment {
 
       if self.dist < 0. {
         self.dist = -self.dist;
-        if let Some(trc) = tracks.get(self.track - 1) {
-          track = trc;
+        if let Some(prev) = prev_track(tracks, self.track) {
+          track = &tracks[prev];
           len = track.len();
-          self.track -= 1;
+          self.track = prev;
           self.dist = len as f32 - self.dist;
         } else {
           self.speed = -self.speed;
@@ -128,9 +861,63 @@ impl Segment {
     self.pos = pos.to_float();
   }
 
-  pub fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
-    graphics::circle(ctx, graphics::DrawMode::Fill, Point2::new(self.pos.0, self.pos.1), 5., 0.2)?;
+  fn lerp_pos(&self, alpha: f32) -> (f32, f32) {
+    let (px, py) = self.prev_pos;
+    let (x, y) = self.pos;
+
+    (px + (x - px) * alpha, py + (y - py) * alpha)
+  }
+
+  pub fn draw(&mut self, ctx: &mut Context, alpha: f32) -> GameResult<()> {
+    let pos = self.lerp_pos(alpha);
+    graphics::circle(ctx, graphics::DrawMode::Fill, Point2::new(pos.0, pos.1), 5., 0.2)?;
 
     Ok(())
   }
 }
+
+fn random_colour<R: Rng>(rnd: &mut R) -> Color {
+  [rnd.gen_range(0.0, 1.0), rnd.gen_range(0.0, 1.0), rnd.gen_range(0.0, 1.0), 1.0].into()
+}
+
+/// how far along the line `dist` on `track` is, counting every piece before it
+fn along(tracks: &[Track], track: usize, dist: f32) -> f32 {
+  tracks[..track].iter().map(|track| track.len()).sum::<f32>() + dist
+}
+
+/// the piece `along` is on and how far along that piece, if it's on the same line as `track`
+fn on_line(tracks: &[Track], mut track: usize, along_line: f32) -> Option<(usize, f32)> {
+  let mut start = along(tracks, track, 0.);
+
+  loop {
+    if along_line < start {
+      track = prev_track(tracks, track)?;
+      start -= tracks[track].len();
+    } else if along_line > start + tracks[track].len() {
+      start += tracks[track].len();
+      track = next_track(tracks, track)?;
+    } else {
+      return Some((track, along_line - start));
+    }
+  }
+}
+
+/// the track following `track`, if they are actually connected
+pub fn next_track(tracks: &[Track], track: usize) -> Option<usize> {
+  let curr = tracks.get(track)?;
+  let next = tracks.get(track + 1)?;
+
+  if curr.end() == next.start() { Some(track + 1) } else { None }
+}
+
+/// the track before `track`, if they are actually connected
+pub fn prev_track(tracks: &[Track], track: usize) -> Option<usize> {
+  if track == 0 {
+    return None;
+  }
+
+  let curr = tracks.get(track)?;
+  let prev = tracks.get(track - 1)?;
+
+  if prev.end() == curr.start() { Some(track - 1) } else { None }
+}
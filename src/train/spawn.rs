@@ -0,0 +1,213 @@
+use rand::XorShiftRng;
+
+use ggez::{
+  Context,
+  graphics::{self, Point2, Font, DrawMode},
+  GameResult,
+};
+
+use path::track::{
+  Track,
+  TrackPiece,
+};
+
+use ui;
+use world::Command;
+
+use super::{
+  traction::Traction,
+  Train,
+  next_track,
+  prev_track,
+};
+
+const FIELDS: [&str; 5] = ["engine", "cars", "spacing", "car length", "speed"];
+// the longest train that can be put together
+pub const MAX_CARS: usize = 12;
+
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct SpawnConfig {
+  pub traction: Traction,
+  pub cars: usize,
+  pub spacing: f32,
+  pub car_len: f32,
+  pub speed: f32,
+}
+
+impl SpawnConfig {
+  /// length of the whole consist from the first to the last axle
+  pub fn len(&self) -> f32 {
+    self.cars as f32 * self.car_len + (self.cars as f32 - 1.) * self.spacing
+  }
+
+  /// whether it makes a train at all, replays and scenarios can ask for anything
+  pub fn valid(&self) -> bool {
+    let sizes = [self.spacing, self.car_len, self.speed];
+
+    self.cars > 0 && sizes.iter().all(|size| size.is_finite()) && self.spacing >= 0. && self.car_len > 0. && self.speed > 0.
+  }
+}
+
+impl Default for SpawnConfig {
+  fn default() -> Self {
+    SpawnConfig {
+      traction: Traction::default(),
+      cars: 4,
+      spacing: 10.,
+      car_len: 40.,
+      speed: 200.,
+    }
+  }
+}
+
+/// placement tool for new trains, shows a preview of the train under the cursor
+pub struct Spawner {
+  pub config: SpawnConfig,
+  forward: bool,
+  field: usize,
+  // track index and distance along it
+  target: Option<(usize, f32)>,
+}
+
+impl Spawner {
+  pub fn new() -> Self {
+    Spawner {
+      config: SpawnConfig::default(),
+      forward: true,
+      field: 0,
+      target: None,
+    }
+  }
+
+  /// `target` is the track piece under the cursor and how far along it is
+  pub fn hover(&mut self, target: Option<(usize, f32)>) {
+    self.target = target;
+  }
+
+  pub fn flip(&mut self) {
+    self.forward = !self.forward;
+  }
+
+  pub fn select_field(&mut self, step: i32) {
+    let len = FIELDS.len() as i32;
+    self.field = ((self.field as i32 + step + len) % len) as usize;
+  }
+
+  pub fn adjust(&mut self, step: i32) {
+    let step = step as f32;
+    let cfg = &mut self.config;
+
+    match self.field {
+      0 => {
+        let len = Traction::ALL.len() as i32;
+        let i = Traction::ALL.iter().position(|&t| t == cfg.traction).expect("every traction is listed") as i32;
+        cfg.traction = Traction::ALL[((i + step as i32 + len) % len) as usize];
+      }
+      1 => cfg.cars = (cfg.cars as f32 + step).max(1.).min(MAX_CARS as f32) as usize,
+      2 => cfg.spacing = (cfg.spacing + step * 2.).max(2.).min(40.),
+      3 => cfg.car_len = (cfg.car_len + step * 5.).max(10.).min(80.),
+      4 => cfg.speed = (cfg.speed + step * 20.).max(20.).min(600.),
+      _ => unreachable!("there are only {} fields", FIELDS.len()),
+    }
+
+    cfg.speed = cfg.speed.min(cfg.traction.max_speed());
+  }
+
+  /// the train that would be placed at the cursor and whether it fits on the track,
+  /// `wired` says which pieces electric trains can go on
+  fn build(&self, tracks: &Vec<Track>, wired: &[bool]) -> Option<(Command, bool)> {
+    let (track, along) = self.target?;
+    let config = self.config;
+    let len = config.len();
+
+    // the head sits at the cursor, the rest of the consist trails behind it
+    let (speed, dist) = if self.forward {
+      (config.speed, along - len)
+    } else {
+      (-config.speed, along)
+    };
+
+    let powered = |track: usize| !config.traction.needs_wires() || wired[track];
+    let valid = fits(tracks, track, along, len, self.forward, powered);
+
+    Some((Command::SpawnTrain { track, dist, speed, config }, valid))
+  }
+
+  pub fn spawn(&self, tracks: &Vec<Track>, wired: &[bool]) -> Option<Command> {
+    match self.build(tracks, wired) {
+      Some((cmd, true)) => Some(cmd),
+      _ => None,
+    }
+  }
+
+  pub fn draw(&self, ctx: &mut Context, tracks: &Vec<Track>, wired: &[bool]) -> GameResult<()> {
+    match self.build(tracks, wired) {
+      Some((Command::SpawnTrain { track, dist, speed, config }, true)) => {
+        // the preview gets its own rng so drawing doesn't change the simulation
+        let mut rng = XorShiftRng::new_unseeded();
+
+        let mut train = Train::new(&mut rng, speed, track, dist, (config.cars, config.spacing, config.car_len), config.traction);
+        train.place(tracks);
+        train.draw_coloured(ctx, [0.0, 0.7, 0.2, 0.5].into(), 1.)
+      }
+      Some((_, false)) => {
+        // the consist doesn't fit, just mark the spot
+        let (track, along) = self.target.expect("build only succeeds with a target");
+        let pos = tracks[track].at(along);
+
+        graphics::set_color(ctx, [0.9, 0.1, 0.1, 0.8].into())?;
+        graphics::circle(ctx, DrawMode::Line(3.), pos.into(), 10., 0.2)
+      }
+      _ => Ok(()),
+    }
+  }
+
+  pub fn draw_dialog(&self, ctx: &mut Context, font: &Font, pos: Point2, hints: &[String]) -> GameResult<()> {
+    let cfg = self.config;
+    let values = [
+      format!("{} ({})", cfg.traction.name(), cfg.traction.price()),
+      format!("{}", cfg.cars),
+      format!("{:.0}", cfg.spacing),
+      format!("{:.0}", cfg.car_len),
+      format!("{:.0}", cfg.speed),
+    ];
+
+    let mut lines = vec![
+      format!("new train ({})", if self.forward { "forward" } else { "backward" }),
+    ];
+
+    for (i, (name, value)) in FIELDS.iter().zip(values.iter()).enumerate() {
+      let marker = if i == self.field { ">" } else { " " };
+      lines.push(format!("{} {}: {}", marker, name, value));
+    }
+
+    lines.extend(hints.iter().cloned());
+
+    ui::draw_panel(ctx, font, pos, &lines)
+  }
+}
+
+/// checks that there's `len` of connected track behind the head of a train, all of it `powered`
+pub fn fits<F: Fn(usize) -> bool>(tracks: &Vec<Track>, track: usize, along: f32, len: f32, forward: bool, powered: F) -> bool {
+  let mut room = if forward { along } else { tracks[track].len() - along };
+  let mut curr = track;
+
+  if !powered(track) {
+    return false;
+  }
+
+  while room < len {
+    let other = if forward { prev_track(tracks, curr) } else { next_track(tracks, curr) };
+
+    match other {
+      Some(other) if powered(other) => {
+        room += tracks[other].len();
+        curr = other;
+      }
+      _ => return false,
+    }
+  }
+
+  true
+}
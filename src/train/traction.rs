@@ -0,0 +1,65 @@
+/// what pulls a train
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq, Deserialize)]
+pub enum Traction {
+  Steam,
+  Diesel,
+  // only runs under the wires
+  Electric,
+}
+
+impl Traction {
+  pub const ALL: [Traction; 3] = [
+    Traction::Steam,
+    Traction::Diesel,
+    Traction::Electric,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Traction::Steam => "steam",
+      Traction::Diesel => "diesel",
+      Traction::Electric => "electric",
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Traction> {
+    Traction::ALL.iter().cloned().find(|traction| traction.name() == name)
+  }
+
+  /// the fastest it pulls a train, whatever the train is set to
+  pub fn max_speed(&self) -> f32 {
+    match self {
+      Traction::Steam => 160.,
+      Traction::Diesel => 240.,
+      Traction::Electric => 320.,
+    }
+  }
+
+  /// what it costs to buy a train with it
+  pub fn price(&self) -> i64 {
+    match self {
+      Traction::Steam => 200,
+      Traction::Diesel => 400,
+      Traction::Electric => 600,
+    }
+  }
+
+  /// what it costs to run every second, on top of the cars
+  pub fn running_cost(&self) -> i64 {
+    match self {
+      Traction::Steam => 6,
+      Traction::Diesel => 4,
+      Traction::Electric => 2,
+    }
+  }
+
+  pub fn needs_wires(&self) -> bool {
+    *self == Traction::Electric
+  }
+}
+
+impl Default for Traction {
+  fn default() -> Self {
+    Traction::Diesel
+  }
+}
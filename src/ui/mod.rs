@@ -0,0 +1,49 @@
+use ggez::{
+  graphics::{self, Point2, Rect, DrawMode, Font, Text},
+  GameResult,
+  Context,
+};
+
+const LINE_HEIGHT: f32 = 18.;
+const PADDING: f32 = 6.;
+
+/// draws a round sign with `text` on it, centred on `pos`
+pub fn draw_sign(ctx: &mut Context, font: &Font, pos: Point2, text: &str) -> GameResult<()> {
+  let text = Text::new(ctx, text, font)?;
+  let radius = text.width().max(text.height()) as f32 / 2. + 3.;
+
+  graphics::set_color(ctx, [1.0, 1.0, 1.0, 1.0].into())?;
+  graphics::circle(ctx, DrawMode::Fill, pos, radius, 0.2)?;
+
+  graphics::set_color(ctx, [0.8, 0.1, 0.1, 1.0].into())?;
+  graphics::circle(ctx, DrawMode::Line(2.), pos, radius, 0.2)?;
+
+  graphics::set_color(ctx, [0.0, 0.0, 0.0, 1.0].into())?;
+  let dest = Point2::new(pos.x - text.width() as f32 / 2., pos.y - text.height() as f32 / 2.);
+  graphics::draw(ctx, &text, dest, 0.)
+}
+
+/// draws a box at `pos` (top left) with one line of text per entry
+pub fn draw_panel(ctx: &mut Context, font: &Font, pos: Point2, lines: &[String]) -> GameResult<()> {
+  let texts = lines.iter()
+      .map(|line| Text::new(ctx, line, font))
+      .collect::<GameResult<Vec<Text>>>()?;
+
+  let width = texts.iter().map(|t| t.width()).max().unwrap_or(0) as f32;
+  let height = texts.len() as f32 * LINE_HEIGHT;
+
+  let rect = Rect::new(pos.x, pos.y, width + 2. * PADDING, height + 2. * PADDING);
+
+  graphics::set_color(ctx, [1.0, 1.0, 1.0, 0.85].into())?;
+  graphics::rectangle(ctx, DrawMode::Fill, rect)?;
+
+  graphics::set_color(ctx, [0.0, 0.0, 0.0, 1.0].into())?;
+  graphics::rectangle(ctx, DrawMode::Line(1.), rect)?;
+
+  for (i, text) in texts.iter().enumerate() {
+    let dest = Point2::new(pos.x + PADDING, pos.y + PADDING + i as f32 * LINE_HEIGHT);
+    graphics::draw(ctx, text, dest, 0.)?;
+  }
+
+  Ok(())
+}
@@ -0,0 +1,1072 @@
+use std::collections::HashSet;
+
+use rand::{SeedableRng, XorShiftRng};
+
+use buffer::Buffer;
+use cargo::{Cargo, Parcel};
+use config::Grid;
+use crossing::{self, Crossing};
+use depot::{self, Depot};
+use finance::Finances;
+use industry::{self, Industry, Kind};
+use path::{
+  track::{Track, TrackPiece},
+  Path,
+  Dir,
+  Pos,
+};
+use signal::Signal;
+use station::{self, Station};
+use terrain::{Ground, Terrain};
+use town::Town;
+use train::{
+  self,
+  spawn::{self, SpawnConfig},
+  Rules,
+  Status,
+  Train,
+};
+
+/// length of a single simulation step in seconds
+pub const TICK: f32 = 1. / 60.;
+pub const TICKS_PER_SECOND: u64 = 60;
+
+// how long trains stand at a station
+const DWELL: u32 = 2 * TICKS_PER_SECOND as u32;
+// what each car costs to run every second
+const RUNNING_COST: i64 = 2;
+// how often towns check whether they get to grow
+const GROWTH_TICKS: u64 = 30 * TICKS_PER_SECOND;
+// what it costs to put up a depot
+pub const DEPOT_COST: i64 = 500;
+// how long trains stand at a depot being serviced and what it costs
+const SERVICE_TIME: u32 = 5 * TICKS_PER_SECOND as u32;
+const SERVICE_COST: i64 = 100;
+// how far past the end of the line a new train can reach
+const SLACK: f32 = 0.01;
+
+/// everything a player can do that changes the world
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
+pub enum Command {
+  // the last one lays double track
+  StartPath(Pos, Dir, bool),
+  AddWaypoint(Pos),
+  RemoveWaypoint,
+  CancelPath,
+  CommitPath(Pos),
+  RemoveTrack(usize),
+  PlaceSignal(usize, f32),
+  ToggleSignal(usize),
+  RemoveSignal(usize),
+  PlaceStation(usize, f32),
+  RemoveStation(usize),
+  // puts up or takes down the speed limit on a piece
+  SetLimit(usize, Option<f32>),
+  // strings wires over a piece or takes them down
+  Electrify(usize, bool),
+  PlaceIndustry(Kind, (i32, i32)),
+  RemoveIndustry(usize),
+  // at the end or the start of a piece
+  PlaceDepot(usize, bool),
+  RemoveDepot(usize),
+  // a new train that waits in the depot until it's put out
+  BuyTrain(usize, SpawnConfig),
+  // puts a train out of a depot onto the line
+  ReleaseTrain(usize, usize),
+  SpawnTrain {
+    track: usize,
+    dist: f32,
+    speed: f32,
+    config: SpawnConfig,
+  },
+  StopTrain(usize),
+  ReverseTrain(usize),
+  // keeps this many cars, counted from the head, and leaves the rest standing
+  SplitTrain(usize, usize),
+  // puts an empty car on behind a train standing on the line or takes its last one off
+  AddCar(usize),
+  RemoveCar(usize),
+  // sends the train back to a depot or calls that off
+  SendToDepot(usize),
+  RemoveTrain(usize),
+}
+
+/// things that happened during the last step
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
+pub enum Event {
+  // the train turned around at the end of the line
+  Arrived(usize),
+  // the train ran into the buffers or took a turn too fast
+  Crashed(usize),
+  Collision(usize, usize),
+  // the second train got coupled onto the first and isn't in the list anymore
+  Coupled(usize, usize),
+  // the train delivered cargo worth this much
+  Delivered(usize, i64),
+  // the train went into a depot and isn't in the list anymore
+  Stored(usize),
+}
+
+/// everything the simulation needs, advanced in fixed steps so runs are reproducible
+pub struct World {
+  pub grid: Grid,
+  pub terrain: Terrain,
+  // money left for building, unlimited without one
+  pub budget: Option<i64>,
+  pub finances: Finances,
+  pub tracks: Vec<Track>,
+  pub trains: Vec<Train>,
+  pub signals: Vec<Signal>,
+  // speed limits the player put up, one for every piece
+  pub limits: Vec<Option<f32>>,
+  // trains only keep to the limits that were put up and come off turns they take too fast
+  pub derailments: bool,
+  // which pieces have wires over them for electric trains, one for every piece
+  pub electrified: Vec<bool>,
+  // worked out from the tracks whenever they change
+  pub crossings: Vec<Crossing>,
+  pub buffers: Vec<Buffer>,
+  pub stations: Vec<Station>,
+  pub depots: Vec<Depot>,
+  pub industries: Vec<Industry>,
+  pub towns: Vec<Town>,
+  // the path that's currently being laid out
+  pub path: Option<Path>,
+  pub tick: u64,
+  pub rng: XorShiftRng,
+  pub events: Vec<Event>,
+  // pairs of trains that are touching right now
+  contacts: Vec<(usize, usize)>,
+}
+
+impl World {
+  pub fn new(seed: u32, grid: Grid) -> Self {
+    World {
+      grid,
+      terrain: Terrain::new(grid),
+      budget: None,
+      finances: Finances::new(),
+      tracks: Vec::new(),
+      trains: Vec::new(),
+      signals: Vec::new(),
+      limits: Vec::new(),
+      derailments: false,
+      electrified: Vec::new(),
+      crossings: Vec::new(),
+      buffers: Vec::new(),
+      stations: Vec::new(),
+      depots: Vec::new(),
+      industries: Vec::new(),
+      towns: Vec::new(),
+      path: None,
+      tick: 0,
+      // xorshift can't be seeded with all zeros, so only the first word is ours
+      rng: XorShiftRng::from_seed([seed, 0x193a_6754, 0xa8a7_d469, 0x9783_0e05]),
+      events: Vec::new(),
+      contacts: Vec::new(),
+    }
+  }
+
+  pub fn apply(&mut self, cmd: &Command) {
+    match *cmd {
+      Command::StartPath(pos, dir, double) => {
+        let blocked = self.towns.iter().flat_map(|town| town.buildings().iter().cloned()).collect();
+        let taken = self.tracks.iter().flat_map(|track| track.cells(self.grid.cell_size)).collect();
+        self.path = Some(Path::new(pos, dir, self.grid, self.terrain.clone(), blocked, taken, double));
+      }
+
+      Command::AddWaypoint(to) => {
+        if let Some(ref mut path) = self.path {
+          path.add_waypoint(to);
+        }
+      }
+
+      Command::RemoveWaypoint => {
+        // taking away the last leg leaves nothing to build
+        if !self.path.as_mut().map_or(true, |path| path.remove_waypoint()) {
+          self.path = None;
+        }
+      }
+
+      Command::CancelPath => {
+        self.path = None;
+      }
+
+      Command::CommitPath(to) => {
+        if let Some(mut path) = self.path.take() {
+          path.add_path(to);
+          let cost = path.cost(&self.terrain);
+
+          // carrying on from the end of the last piece would run the line through a depot there
+          let last = self.tracks.len().wrapping_sub(1);
+          let through_depot = self.tracks.last().map_or(false, |piece| piece.end() == path.start())
+              && self.depots.iter().any(|depot| depot.at_end && depot.track == last);
+
+          if path.has_route() && self.can_afford(cost) && !through_depot {
+            self.spend(cost);
+            self.tracks.append(&mut path.into_pieces());
+            self.tracks_changed();
+          } else {
+            // nothing gets built without a route or the money for it, keep going instead
+            self.path = Some(path);
+          }
+        }
+      }
+
+      Command::RemoveTrack(track) => {
+        // pulling track out from under a train would leave it floating, or with nowhere to come out of a depot
+        let floating = self.trains.iter().any(|train| train.on_track(track));
+        let shut_in = self.depots.iter().any(|depot| depot.track == track && !depot.is_empty());
+        // the pieces either side would join up and leave a depot in the middle of the line
+        let joined = track > 0 && self.tracks.get(track + 1).map_or(false, |next| self.tracks[track - 1].end() == next.start());
+        let buried = joined && self.depots.iter().any(|depot| {
+          if depot.at_end { depot.track == track - 1 } else { depot.track == track + 1 }
+        });
+
+        if track < self.tracks.len() && !floating && !shut_in && !buried {
+          self.tracks.remove(track);
+          self.limits.remove(track);
+          self.electrified.remove(track);
+
+          self.signals.retain(|signal| signal.track != track);
+          self.stations.retain(|station| station.track != track);
+          self.depots.retain(|depot| depot.track != track);
+
+          for train in self.trains.iter_mut() {
+            train.track_removed(track);
+            train.forget_station();
+          }
+          for signal in self.signals.iter_mut().filter(|signal| signal.track > track) {
+            signal.track -= 1;
+          }
+          for station in self.stations.iter_mut().filter(|station| station.track > track) {
+            station.track -= 1;
+          }
+          for depot in self.depots.iter_mut().filter(|depot| depot.track > track) {
+            depot.track -= 1;
+          }
+
+          self.tracks_changed();
+        }
+      }
+
+      Command::PlaceSignal(track, dist) => {
+        if track < self.tracks.len() {
+          self.signals.push(Signal::new(track, dist));
+        }
+      }
+
+      Command::ToggleSignal(signal) => {
+        if let Some(signal) = self.signals.get_mut(signal) {
+          signal.toggle();
+        }
+      }
+
+      Command::RemoveSignal(signal) => {
+        if signal < self.signals.len() {
+          self.signals.remove(signal);
+        }
+      }
+
+      Command::PlaceStation(track, dist) => {
+        if track < self.tracks.len() {
+          self.stations.push(Station::new(track, dist));
+        }
+      }
+
+      Command::RemoveStation(station) => {
+        if station < self.stations.len() {
+          self.stations.remove(station);
+
+          for train in self.trains.iter_mut() {
+            train.forget_station();
+          }
+        }
+      }
+
+      Command::SetLimit(track, limit) => {
+        if let Some(current) = self.limits.get_mut(track) {
+          *current = limit;
+        }
+      }
+
+      Command::Electrify(track, on) => {
+        if track < self.tracks.len() && self.electrified[track] != on {
+          let cost = self.tracks[track].wiring_cost(&self.grid);
+          // electric trains would be stranded without the wires
+          let stranded = self.trains.iter()
+              .any(|train| train.traction().map_or(false, |traction| traction.needs_wires()) && train.on_track(track));
+
+          if on && self.can_afford(cost) {
+            self.spend(cost);
+            self.electrified[track] = true;
+          } else if !on && !stranded {
+            self.electrified[track] = false;
+          }
+        }
+      }
+
+      Command::PlaceIndustry(kind, cell) => {
+        let (w, h) = (self.grid.size.0 as i32, self.grid.size.1 as i32);
+        let fits = cell.0 >= 0 && cell.1 >= 0 && cell.0 + industry::SIZE <= w && cell.1 + industry::SIZE <= h;
+
+        // industries can't sit on top of each other
+        let free = (0..industry::SIZE * industry::SIZE).all(|i| {
+          let spot = (cell.0 + i % industry::SIZE, cell.1 + i / industry::SIZE);
+          self.industries.iter().all(|other| !other.covers(spot))
+        });
+
+        if fits && free {
+          self.industries.push(Industry::new(kind, cell));
+        }
+      }
+
+      Command::RemoveIndustry(industry) => {
+        if industry < self.industries.len() {
+          self.industries.remove(industry);
+        }
+      }
+
+      Command::PlaceDepot(track, at_end) => {
+        let taken = self.depots.iter().any(|depot| depot.track == track && depot.at_end == at_end);
+
+        if track < self.tracks.len() && !taken && Depot::fits(&self.tracks, track, at_end) && self.can_afford(DEPOT_COST) {
+          self.spend(DEPOT_COST);
+          self.depots.push(Depot::new(track, at_end));
+        }
+      }
+
+      Command::RemoveDepot(depot) => {
+        // the trains inside would have nowhere to go
+        if depot < self.depots.len() && self.depots[depot].is_empty() {
+          self.depots.remove(depot);
+
+          // trains on their way to it have nowhere left to go
+          let stranded = (0..self.trains.len())
+              .filter(|&train| self.trains[train].bound() && !self.depot_on_line(train))
+              .collect::<Vec<usize>>();
+
+          for train in stranded {
+            self.trains[train].toggle_bound();
+          }
+        }
+      }
+
+      Command::BuyTrain(depot, config) => {
+        let price = config.traction.price();
+
+        if depot < self.depots.len() && config.valid() && self.can_afford(price) {
+          // it only gets a place on the line when it's put out, until then it's just laid out
+          let train = Train::new(&mut self.rng, config.speed, 0, 0., (config.cars, config.spacing, config.car_len), config.traction);
+          let offsets = train.layout(&self.tracks);
+
+          self.spend(price);
+          self.depots[depot].add(train, offsets);
+        }
+      }
+
+      Command::ReleaseTrain(depot, train) => {
+        let released = match self.depots.get_mut(depot) {
+          Some(depot) => depot.release(train, &self.tracks, &self.electrified, &self.trains),
+          None => None,
+        };
+
+        if let Some(train) = released {
+          self.trains.push(train);
+        }
+      }
+
+      Command::SpawnTrain { track, dist, speed, config } => {
+        let price = config.traction.price();
+
+        if speed.is_finite() && self.can_spawn(track, dist, &config) && self.can_afford(price) {
+          let mut train = Train::new(&mut self.rng, speed, track, dist, (config.cars, config.spacing, config.car_len), config.traction);
+          train.place(&self.tracks);
+
+          self.spend(price);
+          self.trains.push(train);
+        }
+      }
+
+      Command::StopTrain(train) => {
+        if let Some(train) = self.trains.get_mut(train) {
+          train.toggle_stop();
+        }
+      }
+
+      Command::ReverseTrain(train) => {
+        if let Some(train) = self.trains.get_mut(train) {
+          train.reverse();
+        }
+      }
+
+      Command::SplitTrain(train, keep) => {
+        let wagons = match self.trains.get_mut(train) {
+          Some(train) => train.split(&mut self.rng, keep),
+          None => None,
+        };
+
+        if let Some(mut wagons) = wagons {
+          wagons.place(&self.tracks);
+
+          // closely spaced cars still touch right after the cut, they only couple again once
+          // they've been apart and meet again
+          if self.trains[train].touches(&wagons) {
+            self.contacts.push((train, self.trains.len()));
+          }
+
+          self.trains.push(wagons);
+        }
+      }
+
+      Command::AddCar(train) => {
+        if train < self.trains.len() && self.trains[train].add_car(&self.tracks) {
+          // there has to be room for it clear of everything standing behind
+          let blocked = {
+            let added = &self.trains[train];
+            self.trains.iter().enumerate().any(|(i, other)| i != train && other.touches(added))
+          };
+
+          if blocked {
+            self.trains[train].remove_car();
+          }
+        }
+      }
+
+      Command::RemoveCar(train) => {
+        if let Some(train) = self.trains.get_mut(train) {
+          train.remove_car();
+        }
+      }
+
+      Command::SendToDepot(train) => {
+        // it can only go back to a depot at an end of its own line, calling it off always works
+        if train < self.trains.len() && (self.trains[train].bound() || self.depot_on_line(train)) {
+          self.trains[train].toggle_bound();
+        }
+      }
+
+      Command::RemoveTrain(train) => {
+        if train < self.trains.len() {
+          self.trains.remove(train);
+        }
+      }
+    }
+  }
+
+  pub fn step(&mut self) {
+    self.events.clear();
+
+    if self.tick % TICKS_PER_SECOND == 0 {
+      // whatever a town or an industry makes goes to the closest station around it
+      for i in 0..self.towns.len() {
+        let from = self.towns[i].center(self.grid.cell_size);
+
+        for (cargo, amount) in self.towns[i].produce() {
+          if let Some(station) = self.closest_station(from, |pos| self.towns[i].serves(pos, self.grid.cell_size)) {
+            self.stations[station].receive(cargo, amount);
+          }
+        }
+      }
+
+      for i in 0..self.industries.len() {
+        if let Some((cargo, amount)) = self.industries[i].produce() {
+          let from = self.industries[i].center(self.grid.cell_size);
+
+          if let Some(station) = self.closest_station(from, |pos| self.industries[i].serves(pos, self.grid.cell_size)) {
+            self.stations[station].receive(cargo, amount);
+          }
+        }
+      }
+
+      // wagons left standing on their own don't cost anything until an engine takes them again
+      let running = self.trains.iter()
+          .filter(|train| train.traction().is_some())
+          .map(|train| train.cars() as i64 * RUNNING_COST + train.traction().map_or(0, |traction| traction.running_cost()))
+          .sum::<i64>();
+      self.spend(running);
+    }
+
+    if self.tick > 0 && self.tick % GROWTH_TICKS == 0 {
+      self.grow_towns();
+    }
+
+    let rules = (0..self.tracks.len())
+        .map(|track| Rules {
+          limit: self.speed_limit(track).unwrap_or(::std::f32::INFINITY),
+          grade: self.tracks[track].gradient(&self.terrain),
+          wired: self.electrified[track],
+        })
+        .collect::<Vec<Rules>>();
+
+    // trains stop at the next station they get to, unless they're going back to a depot
+    let arrivals = self.trains.iter().enumerate()
+        .filter(|&(_, train)| train.status() == Status::Running && !train.standing() && !train.bound())
+        .filter_map(|(i, train)| {
+          let reach = train.reach(&rules, TICK);
+
+          self.stations.iter().enumerate()
+              .filter(|&(j, _)| train.last_station() != Some(j))
+              .find(|&(_, station)| {
+                train.distance_to(&self.tracks, station.track, station.dist).map_or(false, |dist| dist <= reach)
+              })
+              .map(|(j, _)| (i, j))
+        })
+        .collect::<Vec<(usize, usize)>>();
+
+    for (train, station) in arrivals {
+      self.serve(train, station);
+    }
+
+    let mut turned = Vec::new();
+
+    // one at a time, so a train moving onto a crossing holds back the ones after it
+    for i in 0..self.trains.len() {
+      // how far it can go before a red signal or a crossing someone else is on, so it brakes for them
+      let hold = {
+        let (train, trains, tracks) = (&self.trains[i], &self.trains, &self.tracks);
+        let sight = train.sight(&rules, TICK);
+
+        let signals = self.signals.iter()
+            .filter(|signal| !signal.clear)
+            .filter_map(|signal| train.distance_to(tracks, signal.track, signal.dist))
+            .filter(|&dist| dist <= sight);
+
+        let crossings = self.crossings.iter()
+            .filter_map(|crossing| crossing.ahead(train, tracks).map(|dist| (crossing, dist)))
+            .filter(|&(_, dist)| dist <= sight + crossing::WAIT)
+            .filter(|&(crossing, _)| trains.iter().enumerate().any(|(j, other)| j != i && crossing.holds_back((i, train), (j, other), tracks)))
+            .map(|(_, dist)| dist - crossing::WAIT);
+
+        signals.chain(crossings).fold(None, |closest: Option<f32>, dist| Some(closest.map_or(dist, |closest| closest.min(dist))))
+      };
+
+      // trains don't move while loading, past a red signal or onto a crossing someone else is on
+      let reach = self.trains[i].reach(&rules, TICK);
+      let held = self.trains[i].wait() || hold.map_or(false, |dist| dist <= reach);
+
+      // wagons standing on the line ahead get coupled up to
+      let couple = {
+        let (train, tracks) = (&self.trains[i], &self.tracks);
+
+        self.trains.iter()
+            .filter(|other| other.traction().is_none())
+            .filter_map(|other| train.gap_to(tracks, other))
+            .fold(None, |closest: Option<f32>, gap| Some(closest.map_or(gap, |closest| closest.min(gap))))
+      };
+
+      let crashed = self.trains[i].status() == Status::Crashed;
+
+      if self.trains[i].update(&self.tracks, &rules, couple, hold, self.derailments, if held { 0. } else { TICK }) {
+        self.events.push(Event::Arrived(i));
+        turned.push(i);
+      }
+      if !crashed && self.trains[i].status() == Status::Crashed {
+        self.events.push(Event::Crashed(i));
+      }
+    }
+
+    self.call_at_depots(&turned);
+
+    let (trains, tracks) = (&self.trains, &self.tracks);
+    for crossing in self.crossings.iter_mut() {
+      crossing.occupied = trains.iter().any(|train| crossing.taken_by(train, tracks));
+    }
+
+    // only count a collision once, when the trains first touch, trains that meet gently
+    // enough couple up instead
+    let contacts = self.touching();
+    let mut coupled: Vec<usize> = Vec::new();
+
+    let touched = contacts.iter().cloned()
+        .filter(|pair| !self.contacts.contains(pair))
+        .collect::<Vec<(usize, usize)>>();
+
+    for (i, j) in touched {
+      let (train, other) = (&self.trains[i], &self.trains[j]);
+
+      // running into each other across a crossing is never gentle enough, and only wagons get
+      // coupled on, two engines just bump into each other
+      let wagons = train.traction().is_none() || other.traction().is_none();
+
+      if train.speed() + other.speed() > train::COUPLE_SPEED || !wagons || !train.in_line_with(&self.tracks, other) {
+        self.events.push(Event::Collision(i, j));
+      } else if !coupled.contains(&i) && !coupled.contains(&j) {
+        coupled.push(i);
+        coupled.push(j);
+      }
+    }
+
+    // the later train joins the earlier one, the last pairs first so the numbers still hold
+    let mut pairs = coupled.chunks(2).map(|pair| (pair[0], pair[1])).collect::<Vec<(usize, usize)>>();
+    pairs.sort_by(|a, b| b.1.cmp(&a.1));
+
+    for &(i, j) in pairs.iter() {
+      let other = self.trains.remove(j);
+      self.trains[i].couple(other);
+      self.events.push(Event::Coupled(i, j));
+    }
+
+    self.contacts = if pairs.is_empty() { contacts } else { self.touching() };
+    self.tick += 1;
+    self.finances.step(self.tick);
+  }
+
+  /// trains that turned around at a depot go in if they were sent back, the others get
+  /// serviced there when it's due
+  fn call_at_depots(&mut self, turned: &[usize]) {
+    let calls = turned.iter()
+        .filter_map(|&i| {
+          let (train, tracks) = (&self.trains[i], &self.tracks);
+
+          self.depots.iter()
+              .position(|depot| train.near(depot.pos(tracks), depot::REACH))
+              .map(|depot| (i, depot))
+        })
+        .collect::<Vec<(usize, usize)>>();
+
+    // the last ones first so the numbers still hold
+    for &(i, depot) in calls.iter().rev() {
+      if self.trains[i].bound() {
+        let train = self.trains.remove(i);
+        self.depots[depot].store(train, &self.tracks);
+        self.events.push(Event::Stored(i));
+
+        // the touching pairs from the last step move along with the trains
+        self.contacts = self.contacts.iter()
+            .filter(|&&(a, b)| a != i && b != i)
+            .map(|&(a, b)| (if a > i { a - 1 } else { a }, if b > i { b - 1 } else { b }))
+            .collect();
+      } else if self.trains[i].service_due() {
+        self.trains[i].service(SERVICE_TIME);
+        self.spend(SERVICE_COST);
+      }
+    }
+  }
+
+  /// whether there's a depot at either end of the line the train is on, the only ones it
+  /// can go back to
+  pub fn depot_on_line(&self, train: usize) -> bool {
+    let tracks = &self.tracks;
+    let (mut first, mut last) = (self.trains[train].track(), self.trains[train].track());
+
+    while let Some(prev) = train::prev_track(tracks, first) {
+      first = prev;
+    }
+    while let Some(next) = train::next_track(tracks, last) {
+      last = next;
+    }
+
+    self.depots.iter().any(|depot| if depot.at_end { depot.track == last } else { depot.track == first })
+  }
+
+  /// every pair of trains touching each other
+  fn touching(&self) -> Vec<(usize, usize)> {
+    let mut contacts = Vec::new();
+
+    for (i, train) in self.trains.iter().enumerate() {
+      for (j, other) in self.trains.iter().enumerate().skip(i + 1) {
+        if train.touches(other) {
+          contacts.push((i, j));
+        }
+      }
+    }
+
+    contacts
+  }
+
+  /// whether a train laid out by `config` with its tail `dist` along `track` is all on connected
+  /// track it can run on
+  pub fn can_spawn(&self, track: usize, dist: f32, config: &SpawnConfig) -> bool {
+    if track >= self.tracks.len() || !dist.is_finite() || !config.valid() {
+      return false;
+    }
+
+    let tracks = &self.tracks;
+    let (mut track, mut dist) = (track, dist);
+
+    // the tail can be on a piece before or after `track`
+    while dist < 0. {
+      match train::prev_track(tracks, track) {
+        Some(prev) => {
+          track = prev;
+          dist += tracks[prev].len();
+        }
+        None => return false,
+      }
+    }
+    while dist > tracks[track].len() {
+      match train::next_track(tracks, track) {
+        Some(next) => {
+          dist -= tracks[track].len();
+          track = next;
+        }
+        None => return false,
+      }
+    }
+
+    let electrified = &self.electrified;
+    let powered = |track: usize| !config.traction.needs_wires() || electrified[track];
+    // a train placed right up to the end of the line shouldn't be turned away over rounding
+    let len = config.len() - SLACK;
+
+    // counted from the tail, the rest of the train is ahead of it
+    spawn::fits(tracks, track, dist, len, false, powered)
+  }
+
+  /// what trains keep to on a piece, the limit put up or how fast the piece can be taken,
+  /// whichever is lower, with derailments on they only know about the limits put up
+  pub fn speed_limit(&self, track: usize) -> Option<f32> {
+    let turn = if self.derailments { None } else { self.tracks[track].max_speed() };
+
+    match (self.limits[track], turn) {
+      (Some(limit), Some(turn)) => Some(limit.min(turn)),
+      (limit, turn) => limit.or(turn),
+    }
+  }
+
+  /// works out everything that depends on how the tracks join up
+  fn tracks_changed(&mut self) {
+    self.limits.resize(self.tracks.len(), None);
+    self.electrified.resize(self.tracks.len(), false);
+    self.crossings = Crossing::find(&self.tracks);
+    self.buffers = Buffer::find(&self.tracks);
+  }
+
+  /// unloads everything that came from elsewhere and fills the train back up
+  fn serve(&mut self, train: usize, station: usize) {
+    let at = self.stations[station].pos(&self.tracks);
+    let (tick, cell_size) = (self.tick, self.grid.cell_size);
+
+    let delivered = {
+      let industries = &self.industries;
+      let accepts = |cargo: Cargo| cargo.everywhere() || industries.iter().any(|industry| {
+        industry.kind.accepts(cargo) && industry.serves(at, cell_size)
+      });
+
+      self.trains[train].unload(at, accepts)
+    };
+
+    // industries get what they asked for
+    for parcel in delivered.iter().filter(|parcel| !parcel.cargo.everywhere()) {
+      let industry = self.industries.iter_mut()
+          .find(|industry| industry.kind.accepts(parcel.cargo) && industry.serves(at, cell_size));
+
+      if let Some(industry) = industry {
+        industry.deliver(parcel.amount);
+      }
+    }
+
+    let revenue = delivered.iter()
+        .map(|parcel| parcel.revenue(at, tick, cell_size))
+        .sum::<i64>();
+
+    if revenue > 0 {
+      self.earn(revenue);
+      self.events.push(Event::Delivered(train, revenue));
+    }
+
+    for &cargo in Cargo::ALL.iter() {
+      let space = self.trains[train].space();
+      let amount = self.stations[station].take(cargo, space);
+
+      if amount > 0 {
+        self.trains[train].load(Parcel { cargo, amount, from: at, loaded: tick });
+      }
+    }
+
+    self.trains[train].arrive(station, DWELL);
+  }
+
+  pub fn can_afford(&self, cost: i64) -> bool {
+    self.budget.map_or(true, |budget| cost <= budget)
+  }
+
+  fn earn(&mut self, amount: i64) {
+    if let Some(ref mut budget) = self.budget {
+      *budget += amount;
+    }
+    self.finances.earn(amount);
+  }
+
+  fn spend(&mut self, cost: i64) {
+    if let Some(ref mut budget) = self.budget {
+      *budget -= cost;
+    }
+    self.finances.pay(cost);
+  }
+
+  /// the track piece closest to `cursor` and the distance along it
+  pub fn pick_track(&self, cursor: Pos) -> Option<(usize, f32)> {
+    let pos = cursor.to_float();
+
+    self.tracks.iter().enumerate()
+        .map(|(i, track)| {
+          let (perc, dist) = track.closest(pos);
+          (i, perc * track.len(), dist)
+        })
+        .filter(|&(_, _, dist)| dist <= self.pick_radius())
+        .fold(None, |acc: Option<(usize, f32, f32)>, e| match acc {
+          Some(a) if a.2 <= e.2 => Some(a),
+          _ => Some(e),
+        })
+        .map(|(i, along, _)| (i, along))
+  }
+
+  /// towns with a station that keeps up with their passengers get bigger
+  fn grow_towns(&mut self) {
+    let mut occupied = self.occupied_cells();
+    let (w, h) = (self.grid.size.0 as i32, self.grid.size.1 as i32);
+    let terrain = &self.terrain;
+
+    for i in 0..self.towns.len() {
+      let served = {
+        let town = &self.towns[i];
+        let cell_size = self.grid.cell_size;
+
+        self.stations.iter().any(|station| {
+          town.serves(station.pos(&self.tracks), cell_size) && station.waiting(Cargo::Passengers) < station::MAX_WAITING / 2
+        })
+      };
+
+      if served {
+        let people = self.towns[i].population / 10 + 10;
+
+        {
+          let free = |(x, y): (i32, i32)| {
+            x >= 0 && y >= 0 && x < w && y < h && !occupied.contains(&(x, y)) && terrain.ground((x, y)) != Ground::Water
+          };
+
+          self.towns[i].grow(&mut self.rng, people, free);
+        }
+
+        // the towns after it can't build where it just did
+        occupied.extend(self.towns[i].buildings().iter().cloned());
+      }
+    }
+  }
+
+  /// starts a town around `cell` and builds houses for `population` people
+  pub fn found_town(&mut self, name: String, cell: (i32, i32), population: u32) {
+    let occupied = self.occupied_cells();
+    let (w, h) = (self.grid.size.0 as i32, self.grid.size.1 as i32);
+    let terrain = &self.terrain;
+    let free = |(x, y): (i32, i32)| {
+      x >= 0 && y >= 0 && x < w && y < h && !occupied.contains(&(x, y)) && terrain.ground((x, y)) != Ground::Water
+    };
+
+    if !free(cell) {
+      return;
+    }
+
+    let mut town = Town::new(name, cell, 0);
+    town.grow(&mut self.rng, population, free);
+    self.towns.push(town);
+  }
+
+  /// cells with track, industries or buildings on them
+  fn occupied_cells(&self) -> HashSet<(i32, i32)> {
+    let mut occupied = HashSet::new();
+
+    for track in self.tracks.iter() {
+      occupied.extend(track.cells(self.grid.cell_size));
+    }
+
+    for industry in self.industries.iter() {
+      for i in 0..industry::SIZE * industry::SIZE {
+        occupied.insert((industry.cell.0 + i % industry::SIZE, industry.cell.1 + i / industry::SIZE));
+      }
+    }
+
+    for town in self.towns.iter() {
+      occupied.extend(town.buildings().iter().cloned());
+    }
+
+    occupied
+  }
+
+  /// the station closest to `from` that `serves` accepts
+  fn closest_station<F: Fn(Pos) -> bool>(&self, from: Pos, serves: F) -> Option<usize> {
+    let (cx, cy) = from.to_float();
+
+    self.stations.iter().enumerate()
+        .map(|(i, station)| (i, station.pos(&self.tracks)))
+        .filter(|&(_, pos)| serves(pos))
+        .map(|(i, pos)| {
+          let (x, y) = pos.to_float();
+          (i, ((x - cx).powi(2) + (y - cy).powi(2)).sqrt())
+        })
+        .fold(None, |acc: Option<(usize, f32)>, e| match acc {
+          Some(a) if a.1 <= e.1 => Some(a),
+          _ => Some(e),
+        })
+        .map(|(i, _)| i)
+  }
+
+  pub fn pick_industry(&self, cursor: Pos) -> Option<usize> {
+    let spot = self.cell_at(cursor);
+    self.industries.iter().position(|industry| industry.covers(spot))
+  }
+
+  pub fn pick_town(&self, cursor: Pos) -> Option<usize> {
+    let spot = self.cell_at(cursor);
+    self.towns.iter().position(|town| town.covers(spot))
+  }
+
+  fn cell_at(&self, pos: Pos) -> (i32, i32) {
+    let cell = self.grid.cell_size as f32;
+    let (x, y) = pos.to_float();
+
+    ((x / cell).floor() as i32, (y / cell).floor() as i32)
+  }
+
+  pub fn pick_signal(&self, cursor: Pos) -> Option<usize> {
+    let spots = self.signals.iter().map(|signal| (signal.track, signal.dist)).collect::<Vec<_>>();
+    self.pick_spot(&spots, cursor)
+  }
+
+  pub fn pick_station(&self, cursor: Pos) -> Option<usize> {
+    let spots = self.stations.iter().map(|station| (station.track, station.dist)).collect::<Vec<_>>();
+    self.pick_spot(&spots, cursor)
+  }
+
+  pub fn pick_depot(&self, cursor: Pos) -> Option<usize> {
+    let spots = self.depots.iter().map(|depot| (depot.track, depot.dist(&self.tracks))).collect::<Vec<_>>();
+    self.pick_spot(&spots, cursor)
+  }
+
+  /// the closest of a list of spots along the tracks
+  fn pick_spot(&self, spots: &[(usize, f32)], cursor: Pos) -> Option<usize> {
+    let (x, y) = cursor.to_float();
+
+    spots.iter().enumerate()
+        .map(|(i, &(track, dist))| {
+          let (sx, sy) = self.tracks[track].at(dist).to_float();
+          (i, ((sx - x).powi(2) + (sy - y).powi(2)).sqrt())
+        })
+        .filter(|&(_, dist)| dist <= self.pick_radius())
+        .fold(None, |acc: Option<(usize, f32)>, e| match acc {
+          Some(a) if a.1 <= e.1 => Some(a),
+          _ => Some(e),
+        })
+        .map(|(i, _)| i)
+  }
+
+  // how far away from something the cursor can be and still pick it
+  fn pick_radius(&self) -> f32 {
+    self.grid.cell_size as f32 / 2.
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use stats::Stats;
+
+  /// a straight line across the grid with a train of `cars` running along it
+  fn line(cars: usize) -> World {
+    let mut world = World::new(7, Grid::default());
+    world.apply(&Command::StartPath(Pos(64, 96), Dir::Right, false));
+    world.apply(&Command::CommitPath(Pos(1216, 96)));
+    world.apply(&Command::SpawnTrain { track: 5, dist: 0., speed: 100., config: SpawnConfig { cars, ..SpawnConfig::default() } });
+    world
+  }
+
+  /// steps until `done` or the time's up, returns how many arrivals there were on the way
+  fn run<F: Fn(&World) -> bool>(world: &mut World, stats: &mut Stats, ticks: u64, done: F) -> u32 {
+    let mut arrivals = 0;
+
+    for _ in 0..ticks {
+      world.step();
+      stats.observe(world);
+      arrivals += world.events.iter().filter(|event| match **event { Event::Arrived(_) => true, _ => false }).count() as u32;
+
+      if done(world) {
+        break;
+      }
+    }
+
+    arrivals
+  }
+
+  #[test]
+  fn splits_and_couples_up_again() {
+    let mut world = line(4);
+    let mut stats = Stats::new();
+    let space = world.trains[0].space();
+
+    let mut arrivals = run(&mut world, &mut stats, 300, |_| false);
+    world.apply(&Command::StopTrain(0));
+    world.apply(&Command::SplitTrain(0, 2));
+
+    assert_eq!(world.trains.len(), 2);
+    assert_eq!((world.trains[0].cars(), world.trains[1].cars()), (2, 2));
+    assert!(world.trains[1].traction().is_none());
+    assert_eq!(world.trains[0].space() + world.trains[1].space(), space);
+
+    // the engine runs off to the end of the line and comes back for its wagons
+    world.apply(&Command::StopTrain(0));
+    arrivals += run(&mut world, &mut stats, 6000, |world| world.trains.len() == 1);
+
+    assert_eq!(world.trains.len(), 1);
+    assert_eq!(world.trains[0].cars(), 4);
+    assert!(world.trains[0].traction().is_some());
+    assert_eq!(world.trains[0].space(), space);
+
+    // the wagons' numbers went to the train they joined
+    let report = stats.report();
+    assert!(report.contains("collisions: 0\n"));
+    assert!(report.contains(&format!("arrivals: {}\n", arrivals)));
+    assert_eq!(report.matches("train #").count(), 1);
+  }
+
+  #[test]
+  fn brakes_for_a_red_signal() {
+    let mut world = line(2);
+    run(&mut world, &mut Stats::new(), 200, |_| false);
+
+    let track = world.trains[0].track() + 6;
+    world.apply(&Command::PlaceSignal(track, 0.));
+    world.apply(&Command::ToggleSignal(0));
+
+    let mut speed = world.trains[0].speed();
+    assert!(speed > 50.);
+
+    for _ in 0..600 {
+      world.step();
+      let now = world.trains[0].speed();
+
+      // it slows down a bit at a time until it's creeping, and then it stands right in front of the signal
+      assert!(speed - now <= 150. * TICK + 0.01 || now == 0. && speed <= 15., "{} to {}", speed, now);
+      assert!(world.trains[0].distance_to(&world.tracks, track, 0.).is_some());
+      speed = now;
+    }
+
+    assert_eq!(speed, 0.);
+    assert!(world.trains[0].distance_to(&world.tracks, track, 0.).unwrap() < 1.);
+  }
+
+  #[test]
+  fn adds_and_takes_off_cars() {
+    let mut world = line(2);
+    let space = world.trains[0].space();
+
+    // not while it's running
+    for _ in 0..100 {
+      world.step();
+    }
+    world.apply(&Command::AddCar(0));
+    assert_eq!(world.trains[0].cars(), 2);
+
+    world.apply(&Command::StopTrain(0));
+    world.apply(&Command::AddCar(0));
+    assert_eq!(world.trains[0].cars(), 3);
+    assert!(world.trains[0].space() > space);
+
+    world.apply(&Command::RemoveCar(0));
+    world.apply(&Command::RemoveCar(0));
+    world.apply(&Command::RemoveCar(0));
+    assert_eq!(world.trains[0].cars(), 1);
+    assert!(world.trains[0].space() < space);
+  }
+}